@@ -1,6 +1,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 use crate::field_types::{Date, Time};
 use crate::{Buffer, FieldType};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Representation for `UtcTimestamp`.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,17 +32,24 @@ impl Timestamp {
     /// This function will panic if the current system time is not a valid
     /// FIX timestamp. This should never happen.
     pub fn utc_now() -> Self {
+        Self::from_system_time(SystemTime::now()).unwrap()
+    }
+
+    /// Converts `time` to a UTC [`Timestamp`] with millisecond precision.
+    /// Returns `None` for times before the UNIX epoch.
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
         use chrono::{Datelike, Timelike};
-        let utc: chrono::DateTime<chrono::Utc> = chrono::Utc::now();
-        let date = Date::new(utc.year() as u32, utc.month(), utc.day());
+        time.duration_since(UNIX_EPOCH).ok()?;
+        let utc = chrono::DateTime::<chrono::Utc>::from(time);
+        let date = Date::new(utc.year() as u32, utc.month(), utc.day())?;
+        // Leap seconds are folded into the last millisecond.
         let time = Time::from_hmsm(
             utc.hour(),
             utc.minute(),
             utc.second(),
-            utc.nanosecond() / 1_000_000,
-        )
-        .unwrap();
-        Self::new(date.unwrap(), time)
+            (utc.nanosecond() / 1_000_000).min(999),
+        )?;
+        Some(Self::new(date, time))
     }
 
     /// Returns the date of `self`.
@@ -54,6 +62,32 @@ impl Timestamp {
         self.time
     }
 
+    /// Converts `self` to a [`SystemTime`]. Returns `None` for timestamps
+    /// before the UNIX epoch.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let date = chrono::NaiveDate::from_ymd_opt(
+            self.date.year() as i32,
+            self.date.month(),
+            self.date.day(),
+        )?;
+        // `chrono` expresses a leap second as the 1000th millisecond onwards
+        // of the second before it.
+        let (second, milli) = match self.time.second() {
+            60 => (59, self.time.milli() + 1000),
+            second => (second, self.time.milli()),
+        };
+        let time = chrono::NaiveTime::from_hms_milli_opt(
+            self.time.hour(),
+            self.time.minute(),
+            second,
+            milli,
+        )?;
+        let millis = chrono::NaiveDateTime::new(date, time)
+            .and_utc()
+            .timestamp_millis();
+        Some(UNIX_EPOCH + Duration::from_millis(u64::try_from(millis).ok()?))
+    }
+
     /// Attempts to convert `self` to a UTC [`chrono::DateTime`]. As `chrono`
     /// might perform additional checks that make such conversion impossible,
     /// the return value of this function might be `None`.
@@ -105,6 +139,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn to_system_time() {
+        let timestamp = Timestamp::parse(b"20240229-13:45:30.250").unwrap();
+        assert_eq!(
+            timestamp.to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_millis(1_709_214_330_250))
+        );
+        let epoch = Timestamp::parse(b"19700101-00:00:00").unwrap();
+        assert_eq!(epoch.to_system_time(), Some(UNIX_EPOCH));
+        let leap_second = Timestamp::parse(b"20161231-23:59:60.500").unwrap();
+        assert_eq!(
+            leap_second.to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_millis(1_483_228_800_500))
+        );
+        let before_epoch = Timestamp::parse(b"19691231-23:59:59").unwrap();
+        assert_eq!(before_epoch.to_system_time(), None);
+    }
+
+    #[test]
+    fn from_system_time() {
+        let time = UNIX_EPOCH + Duration::from_micros(1_709_214_330_250_999);
+        assert_eq!(
+            Timestamp::from_system_time(time),
+            Timestamp::parse(b"20240229-13:45:30.250")
+        );
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(Timestamp::from_system_time(before_epoch), None);
+    }
+
     #[quickcheck]
     fn verify_serialization_behavior(timestamp: Timestamp) -> bool {
         let serialized = timestamp.to_bytes();
@@ -41,9 +41,14 @@
 //!
 //! Logging of [`tagvalue::Message`]s.
 //!
+//! ### `utils-rusqlite`
+//!
+//! Embedded SQLite message store for `session::backends::DatabaseBackend`.
+//!
 //! ### `utils-bytes`, `utils-tokio`
 //!
-//! FIX decoders and encoders that integrate nicely with the Tokio ecosystem.
+//! FIX decoders and encoders that integrate nicely with the Tokio ecosystem,
+//! and `session::Session::run_tcp`.
 //!
 //! ### `json-encoding`
 //!
@@ -102,6 +107,7 @@
 
 mod buffer;
 mod field_access;
+mod tags;
 mod utils;
 
 pub mod definitions;
@@ -0,0 +1,877 @@
+use super::authentication::Authenticator;
+use super::{
+    Authenticate, Backend, Configure, FixConnection, MessageTap, Session, SessionControl,
+    SessionControls, SessionError, SessionHandle, SessionRole, Verify,
+};
+use crate::tags::{
+    BEGIN_STRING, MSG_TYPE, SENDER_COMP_ID, SENDER_SUB_ID, TARGET_COMP_ID, TARGET_SUB_ID,
+};
+use crate::tagvalue::{DecodeError, Decoder};
+use crate::{Dictionary, StreamingDecoder};
+use futures::io::{Chain, Cursor, ReadHalf, WriteHalf};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, FutureExt};
+use futures_timer::Delay;
+use rustc_hash::FxHashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// How long [`Acceptor::serve`] waits after a failed `accept`, at first and at
+/// most.
+#[cfg(feature = "utils-tokio")]
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(5);
+#[cfg(feature = "utils-tokio")]
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Identifies a FIX session from the local point of view: `sender_comp_id` is
+/// always *our* CompID, even when the [`SessionId`] is derived from an inbound
+/// message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionId {
+    begin_string: String,
+    sender_comp_id: String,
+    target_comp_id: String,
+    sender_sub_id: Option<String>,
+    target_sub_id: Option<String>,
+}
+
+impl SessionId {
+    /// Creates a new [`SessionId`] without SubIDs.
+    pub fn new(
+        begin_string: impl Into<String>,
+        sender_comp_id: impl Into<String>,
+        target_comp_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            begin_string: begin_string.into(),
+            sender_comp_id: sender_comp_id.into(),
+            target_comp_id: target_comp_id.into(),
+            sender_sub_id: None,
+            target_sub_id: None,
+        }
+    }
+
+    /// Sets our `SenderSubID <50>`.
+    pub fn with_sender_sub_id(mut self, sender_sub_id: impl Into<String>) -> Self {
+        self.sender_sub_id = Some(sender_sub_id.into());
+        self
+    }
+
+    /// Sets our `TargetSubID <57>`.
+    pub fn with_target_sub_id(mut self, target_sub_id: impl Into<String>) -> Self {
+        self.target_sub_id = Some(target_sub_id.into());
+        self
+    }
+
+    /// Derives the [`SessionId`] of an inbound message, swapping the
+    /// counterparty's sender and target fields. Returns [`None`] if any of
+    /// `BeginString <8>`, `SenderCompID <49>` or `TargetCompID <56>` is
+    /// missing.
+    pub fn from_inbound(message: &crate::tagvalue::Message<&[u8]>) -> Option<Self> {
+        let text = |tag| {
+            message
+                .get_raw(tag)
+                .map(|value| String::from_utf8_lossy(value).into_owned())
+        };
+        Some(Self {
+            begin_string: text(BEGIN_STRING)?,
+            sender_comp_id: text(TARGET_COMP_ID)?,
+            target_comp_id: text(SENDER_COMP_ID)?,
+            sender_sub_id: text(TARGET_SUB_ID),
+            target_sub_id: text(SENDER_SUB_ID),
+        })
+    }
+
+    /// Returns the `BeginString <8>` of this session.
+    pub fn begin_string(&self) -> &str {
+        &self.begin_string
+    }
+
+    /// Returns our `SenderCompID <49>`.
+    pub fn sender_comp_id(&self) -> &str {
+        &self.sender_comp_id
+    }
+
+    /// Returns the counterparty's CompID, i.e. our `TargetCompID <56>`.
+    pub fn target_comp_id(&self) -> &str {
+        &self.target_comp_id
+    }
+
+    /// Returns our `SenderSubID <50>`, if any.
+    pub fn sender_sub_id(&self) -> Option<&str> {
+        self.sender_sub_id.as_deref()
+    }
+
+    /// Returns our `TargetSubID <57>`, if any.
+    pub fn target_sub_id(&self) -> Option<&str> {
+        self.target_sub_id.as_deref()
+    }
+
+    fn without_sub_ids(&self) -> Self {
+        Self::new(
+            self.begin_string.clone(),
+            self.sender_comp_id.clone(),
+            self.target_comp_id.clone(),
+        )
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.begin_string, self.sender_comp_id)?;
+        if let Some(sub_id) = &self.sender_sub_id {
+            write!(f, "/{sub_id}")?;
+        }
+        write!(f, "->{}", self.target_comp_id)?;
+        if let Some(sub_id) = &self.target_sub_id {
+            write!(f, "/{sub_id}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The [`Backend`] and configuration of one session known to an [`Acceptor`].
+#[derive(Debug, Clone)]
+pub struct SessionDefinition<B, C> {
+    /// Cloned into the [`Session`] created on the first connection of this
+    /// session. Later connections reuse that [`Session`], so its messages and
+    /// sequence numbers carry over.
+    pub backend: B,
+    /// The session configuration.
+    pub config: C,
+}
+
+/// The set of sessions that an [`Acceptor`] accepts, indexed by
+/// [`SessionId`].
+#[derive(Debug, Clone)]
+pub struct SessionRegistry<B, C> {
+    sessions: FxHashMap<SessionId, SessionDefinition<B, C>>,
+}
+
+impl<B, C> Default for SessionRegistry<B, C> {
+    fn default() -> Self {
+        Self {
+            sessions: FxHashMap::default(),
+        }
+    }
+}
+
+impl<B, C> SessionRegistry<B, C> {
+    /// Creates an empty [`SessionRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a session, replacing any previous definition with the same
+    /// [`SessionId`].
+    pub fn insert(&mut self, id: SessionId, backend: B, config: C) {
+        self.sessions
+            .insert(id, SessionDefinition { backend, config });
+    }
+
+    /// Removes a session definition.
+    pub fn remove(&mut self, id: &SessionId) -> Option<SessionDefinition<B, C>> {
+        self.sessions.remove(id)
+    }
+
+    /// Looks up a session definition. An exact match is preferred; otherwise
+    /// a definition registered without SubIDs matches any SubIDs.
+    pub fn get(&self, id: &SessionId) -> Option<&SessionDefinition<B, C>> {
+        self.get_key_value(id).map(|(_, definition)| definition)
+    }
+
+    /// Like [`SessionRegistry::get`], but also returns the [`SessionId`] the
+    /// definition is registered under.
+    pub fn get_key_value(&self, id: &SessionId) -> Option<(&SessionId, &SessionDefinition<B, C>)> {
+        self.sessions
+            .get_key_value(id)
+            .or_else(|| self.sessions.get_key_value(&id.without_sub_ids()))
+    }
+
+    /// Returns the number of registered sessions.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Returns `true` if no sessions are registered.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Iterates over all registered session IDs.
+    pub fn ids(&self) -> impl Iterator<Item = &SessionId> {
+        self.sessions.keys()
+    }
+}
+
+/// Errors that prevent an [`Acceptor`] from establishing a session.
+#[derive(Debug, thiserror::Error)]
+pub enum AcceptorError {
+    /// The transport failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// The first inbound message can't be decoded.
+    #[error("Invalid inbound message: {0}")]
+    Decode(#[from] DecodeError),
+    /// The counterparty didn't send a Logon in time.
+    #[error("No Logon <A> received in time")]
+    LogonTimeout,
+    /// The counterparty closed the transport before sending a Logon.
+    #[error("Transport closed before Logon <A>")]
+    Closed,
+    /// The first inbound message is not a usable `Logon <A>`.
+    #[error("Invalid Logon <A>: {0}")]
+    InvalidLogon(String),
+    /// No session with this ID is registered.
+    #[error("Unknown session {0}")]
+    UnknownSession(SessionId),
+    /// The session is already connected on another transport.
+    #[error("Session {0} is already connected")]
+    AlreadyConnected(SessionId),
+}
+
+/// Prepares every new [`Session`] of an [`Acceptor`].
+type SetupFn<B, C, V> = dyn Fn(&SessionId, &mut Session<B, C, V>) + Send + Sync;
+
+struct SessionSetup<B, C, V>(Box<SetupFn<B, C, V>>);
+
+impl<B, C, V> fmt::Debug for SessionSetup<B, C, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SessionSetup")
+    }
+}
+
+/// The [`Session`] of a registered session, once it has connected.
+#[derive(Debug)]
+struct SessionSlot<B, C, V> {
+    /// [`None`] while a transport is running it.
+    session: Option<Session<B, C, V>>,
+    control: SessionControl,
+}
+
+type SessionSlots<B, C, V> = Arc<Mutex<FxHashMap<SessionId, SessionSlot<B, C, V>>>>;
+
+/// Accepts many FIX sessions on a single listening socket.
+///
+/// Every new transport must start with a `Logon <A>`, whose [`SessionId`]
+/// selects the [`Backend`] and configuration from a [`SessionRegistry`].
+/// Unknown sessions and sessions that are already connected are refused by
+/// closing the transport.
+///
+/// Each registered session has a single [`Session`], created on its first
+/// connection and reused by every later one: sequence numbers, stored
+/// messages, the schedule window and [`SessionControl::disable`] all carry
+/// over from one connection to the next.
+#[derive(Debug)]
+pub struct Acceptor<B, C, V> {
+    registry: SessionRegistry<B, C>,
+    dictionary: Dictionary,
+    verifier: V,
+    logon_timeout: Duration,
+    authenticator: Option<Authenticator>,
+    /// By the [`SessionId`] they are registered under.
+    sessions: SessionSlots<B, C, V>,
+    controls: SessionControls,
+    tap: Option<MessageTap>,
+    setup: Option<SessionSetup<B, C, V>>,
+}
+
+impl<B, C, V> Acceptor<B, C, V>
+where
+    B: Backend,
+    C: Configure,
+    V: Verify + Clone,
+{
+    /// Creates a new [`Acceptor`]. `dictionary` is used to decode inbound
+    /// messages of all sessions.
+    pub fn new(registry: SessionRegistry<B, C>, dictionary: Dictionary, verifier: V) -> Self {
+        Self {
+            registry,
+            dictionary,
+            verifier,
+            logon_timeout: Duration::from_secs(10),
+            authenticator: None,
+            sessions: Arc::default(),
+            controls: SessionControls::new(),
+            tap: None,
+            setup: None,
+        }
+    }
+
+    /// Returns the [`SessionRegistry`] of `self`.
+    pub fn registry(&self) -> &SessionRegistry<B, C> {
+        &self.registry
+    }
+
+    /// Returns the [`SessionControls`] of every session accepted so far. Clone
+    /// it to operate the sessions from elsewhere, e.g. an admin service.
+    pub fn controls(&self) -> &SessionControls {
+        &self.controls
+    }
+
+    /// Sets how long a new transport may stay silent before the first
+    /// `Logon <A>`. 10 seconds by default.
+    pub fn set_logon_timeout(&mut self, timeout: Duration) {
+        self.logon_timeout = timeout;
+    }
+
+    /// Installs the [`Authenticate`] hooks consulted on the `Logon <A>` of
+    /// every accepted session.
+    pub fn set_authenticator(&mut self, authenticator: Arc<dyn Authenticate>) {
+        self.authenticator = Some(Authenticator(authenticator));
+    }
+
+    /// Copies the messages of every accepted session to `tap`. See
+    /// [`FixConnection::set_tap`].
+    pub fn set_tap(&mut self, tap: MessageTap) {
+        self.tap = Some(tap);
+    }
+
+    /// Calls `setup` with every new [`Session`] and the [`SessionId`] it's
+    /// registered under, before it runs for the first time, e.g. to install a
+    /// [`SessionListener`](super::SessionListener), a
+    /// [`Throttle`](super::Throttle), a [`SeqNumStore`](super::SeqNumStore), a
+    /// [`Clock`](super::Clock) or FIXT.1.1 application dictionaries through
+    /// [`Session::connection_mut`].
+    pub fn set_session_setup<F>(&mut self, setup: F)
+    where
+        F: Fn(&SessionId, &mut Session<B, C, V>) + Send + Sync + 'static,
+    {
+        self.setup = Some(SessionSetup(Box::new(setup)));
+    }
+
+    /// Returns `true` if a transport is currently running the session `id`,
+    /// or the registered session that `id` falls back to.
+    pub fn is_connected(&self, id: &SessionId) -> bool {
+        let Some((key, _)) = self.registry.get_key_value(id) else {
+            return false;
+        };
+        self.lock_sessions()
+            .get(key)
+            .is_some_and(|slot| slot.session.is_none())
+    }
+
+    fn lock_sessions(
+        &self,
+    ) -> std::sync::MutexGuard<'_, FxHashMap<SessionId, SessionSlot<B, C, V>>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reads the first `Logon <A>` from `stream` and sets up the matching
+    /// [`Session`]. The Logon itself is processed by the [`Session`] once
+    /// [`AcceptedSession::run`] is called.
+    pub async fn accept<S>(
+        &self,
+        stream: S,
+    ) -> Result<(AcceptedSession<B, C, V, S>, SessionHandle), AcceptorError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (mut reader, mut writer) = stream.split();
+        let result = self.route(&mut reader).await;
+        let (id, guard, handle, logon) = match result {
+            Ok(routed) => routed,
+            Err(err) => {
+                log::warn!("Refusing inbound connection: {err}");
+                writer.close().await.ok();
+                return Err(err);
+            }
+        };
+        log::info!("Accepted session {id}");
+        let accepted = AcceptedSession {
+            id,
+            guard,
+            reader: Cursor::new(logon).chain(reader),
+            writer,
+        };
+        Ok((accepted, handle))
+    }
+
+    async fn route<R>(
+        &self,
+        reader: &mut R,
+    ) -> Result<(SessionId, SessionGuard<B, C, V>, SessionHandle, Vec<u8>), AcceptorError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let (id, logon) = futures::select! {
+            result = self.read_logon(reader).fuse() => result?,
+            () = Delay::new(self.logon_timeout).fuse() => return Err(AcceptorError::LogonTimeout),
+        };
+        let (guard, handle) = self.check_out(&id)?;
+        Ok((id, guard, handle, logon))
+    }
+
+    /// Takes the [`Session`] that `id` is routed to, creating it on its first
+    /// connection. Sessions are keyed by the [`SessionId`] they are
+    /// registered under, so a definition without SubIDs runs at most once,
+    /// whatever the SubIDs of its counterparties.
+    fn check_out(
+        &self,
+        id: &SessionId,
+    ) -> Result<(SessionGuard<B, C, V>, SessionHandle), AcceptorError> {
+        let Some((key, definition)) = self.registry.get_key_value(id) else {
+            return Err(AcceptorError::UnknownSession(id.clone()));
+        };
+        let mut sessions = self.lock_sessions();
+        let (session, handle) = match sessions.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                let slot = entry.get_mut();
+                let Some(mut session) = slot.session.take() else {
+                    return Err(AcceptorError::AlreadyConnected(id.clone()));
+                };
+                let handle = session.new_handle(slot.control.clone());
+                (session, handle)
+            }
+            Entry::Vacant(entry) => {
+                let (session, handle) = self.new_session(key, definition);
+                self.controls.insert(key.clone(), handle.control());
+                entry.insert(SessionSlot {
+                    session: None,
+                    control: handle.control(),
+                });
+                (session, handle)
+            }
+        };
+        let guard = SessionGuard {
+            sessions: self.sessions.clone(),
+            key: key.clone(),
+            session: Some(session),
+        };
+        Ok((guard, handle))
+    }
+
+    fn new_session(
+        &self,
+        key: &SessionId,
+        definition: &SessionDefinition<B, C>,
+    ) -> (Session<B, C, V>, SessionHandle) {
+        let mut connection = FixConnection::new(
+            definition.backend.clone(),
+            definition.config.clone(),
+            self.verifier.clone(),
+        );
+        if let Some(Authenticator(authenticator)) = &self.authenticator {
+            connection.set_authenticator(authenticator.clone());
+        }
+        if let Some(tap) = &self.tap {
+            connection.set_tap(tap.clone());
+        }
+        let (mut session, handle) =
+            Session::new(connection, SessionRole::Acceptor, self.dictionary.clone());
+        if let Some(SessionSetup(setup)) = &self.setup {
+            setup(key, &mut session);
+        }
+        (session, handle)
+    }
+
+    async fn read_logon<R>(&self, reader: &mut R) -> Result<(SessionId, Vec<u8>), AcceptorError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut decoder = Decoder::new(self.dictionary.clone()).streaming(Vec::new());
+        loop {
+            // `num_bytes_required` is a lower bound, so this never reads past
+            // the end of the Logon.
+            reader
+                .read_exact(decoder.fillable())
+                .await
+                .map_err(|err| match err.kind() {
+                    io::ErrorKind::UnexpectedEof => AcceptorError::Closed,
+                    _ => AcceptorError::Io(err),
+                })?;
+            if decoder.try_parse()?.is_some() {
+                break;
+            }
+        }
+        let message = decoder.message();
+        if message.get_raw(MSG_TYPE) != Some(b"A") {
+            return Err(AcceptorError::InvalidLogon(
+                "the first message must be a Logon <A>".to_string(),
+            ));
+        }
+        let id = SessionId::from_inbound(&message).ok_or_else(|| {
+            AcceptorError::InvalidLogon("missing BeginString or CompIDs".to_string())
+        })?;
+        Ok((id, message.as_bytes().to_vec()))
+    }
+
+    /// Accepts connections from `listener` forever, running every session on
+    /// its own Tokio task. The [`SessionHandle`] of each accepted session is
+    /// sent to `sessions`; the loop stops once its receiver is dropped.
+    /// Failures to accept a connection are logged and retried with an
+    /// exponential backoff.
+    #[cfg(feature = "utils-tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "utils-tokio")))]
+    pub async fn serve(
+        self,
+        listener: tokio::net::TcpListener,
+        sessions: futures::channel::mpsc::UnboundedSender<(SessionId, SessionHandle)>,
+    ) -> io::Result<()>
+    where
+        B: Send + Sync + 'static,
+        B::Error: Send,
+        C: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let acceptor = Arc::new(self);
+        let mut backoff = MIN_ACCEPT_BACKOFF;
+        while !sessions.is_closed() {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => {
+                    backoff = MIN_ACCEPT_BACKOFF;
+                    accepted
+                }
+                Err(err) => {
+                    // E.g. EMFILE or ECONNABORTED: the listener itself is
+                    // still fine.
+                    log::error!("Failed to accept a connection, retrying in {backoff:?}: {err}");
+                    Delay::new(backoff).await;
+                    backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                    continue;
+                }
+            };
+            let acceptor = acceptor.clone();
+            let sessions = sessions.clone();
+            tokio::spawn(async move {
+                let (accepted, handle) = match acceptor.accept(stream.compat()).await {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        log::warn!("Connection from {peer} refused: {err}");
+                        return;
+                    }
+                };
+                let id = accepted.id().clone();
+                if sessions.unbounded_send((id.clone(), handle)).is_err() {
+                    return;
+                }
+                if let Err(err) = accepted.run().await {
+                    log::error!("Session {id} terminated: {err}");
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A [`Session`] routed by an [`Acceptor`], ready to run.
+#[derive(Debug)]
+pub struct AcceptedSession<B, C, V, S> {
+    id: SessionId,
+    guard: SessionGuard<B, C, V>,
+    reader: Chain<Cursor<Vec<u8>>, ReadHalf<S>>,
+    writer: WriteHalf<S>,
+}
+
+impl<B, C, V, S> AcceptedSession<B, C, V, S>
+where
+    B: Backend,
+    C: Configure,
+    V: Verify,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the [`SessionId`] the counterparty logged on to.
+    pub fn id(&self) -> &SessionId {
+        &self.id
+    }
+
+    /// Returns an immutable reference to the [`Session`].
+    pub fn session(&self) -> &Session<B, C, V> {
+        self.guard.session()
+    }
+
+    /// Returns a mutable reference to the [`Session`], e.g. to configure its
+    /// [`FixConnection`] before it runs. Settings persist across connections;
+    /// see [`Acceptor::set_session_setup`] to apply them only once.
+    pub fn session_mut(&mut self) -> &mut Session<B, C, V> {
+        self.guard.session_mut()
+    }
+
+    /// Runs the [`Session`] to completion. The session can be accepted again
+    /// afterwards.
+    pub async fn run(self) -> Result<(), SessionError> {
+        let Self {
+            mut guard,
+            reader,
+            writer,
+            ..
+        } = self;
+        guard.session_mut().run_split(reader, writer).await
+    }
+}
+
+/// Holds the [`Session`] of a connected session and hands it back to the
+/// [`Acceptor`] once dropped.
+#[derive(Debug)]
+struct SessionGuard<B, C, V> {
+    sessions: SessionSlots<B, C, V>,
+    key: SessionId,
+    /// Only [`None`] while dropping.
+    session: Option<Session<B, C, V>>,
+}
+
+impl<B, C, V> SessionGuard<B, C, V> {
+    fn session(&self) -> &Session<B, C, V> {
+        self.session.as_ref().expect("the session is checked out")
+    }
+
+    fn session_mut(&mut self) -> &mut Session<B, C, V> {
+        self.session.as_mut().expect("the session is checked out")
+    }
+}
+
+impl<B, C, V> Drop for SessionGuard<B, C, V> {
+    fn drop(&mut self) {
+        if let Some(slot) = self
+            .sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&self.key)
+        {
+            slot.session = self.session.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::session::backends::MemoryBackend;
+    use crate::session::test_utils::{order, session};
+    use crate::session::{Config, NoOpVerifier};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    fn registry() -> SessionRegistry<MemoryBackend, Config> {
+        let mut registry = SessionRegistry::new();
+        for client in ["CLIENT1", "CLIENT2"] {
+            registry.insert(
+                SessionId::new("FIX.4.4", "SERVER", client),
+                MemoryBackend::new("SERVER", client),
+                Config::default(),
+            );
+        }
+        registry
+    }
+
+    fn initiator(sender: &str) -> (Session<MemoryBackend, Config, NoOpVerifier>, SessionHandle) {
+        session(sender, "SERVER", SessionRole::Initiator)
+    }
+
+    fn acceptor() -> Acceptor<MemoryBackend, Config, NoOpVerifier> {
+        Acceptor::new(registry(), Dictionary::fix44().unwrap(), NoOpVerifier)
+    }
+
+    #[test]
+    fn session_id_display_and_sub_id_fallback() {
+        let id = SessionId::new("FIX.4.4", "SERVER", "CLIENT1").with_target_sub_id("DESK");
+        assert_eq!(id.to_string(), "FIX.4.4:SERVER->CLIENT1/DESK");
+        let registry = registry();
+        assert!(registry.get(&id).is_some());
+        assert!(
+            registry
+                .get(&SessionId::new("FIX.4.2", "SERVER", "CLIENT1"))
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn routes_logon_to_registered_session() {
+        let acceptor = acceptor();
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (mut client, client_handle) = initiator("CLIENT2");
+
+        let server = async {
+            let (accepted, mut handle) = acceptor.accept(server_io.compat()).await.unwrap();
+            assert_eq!(
+                accepted.id(),
+                &SessionId::new("FIX.4.4", "SERVER", "CLIENT2")
+            );
+            assert!(acceptor.is_connected(accepted.id()));
+            let run = accepted.run();
+            let app = async {
+                let message = handle.recv().await.unwrap();
+                assert!(message.windows(10).any(|w| w == b"\x0111=ORDER\x01"));
+                client_handle.logout("").unwrap();
+                handle
+            };
+            let (result, _handle) = futures::join!(run, app);
+            result
+        };
+        client_handle.send(b"D", &order("ORDER")).unwrap();
+        let (client_result, server_result) = tokio::join!(client.run(client_io.compat()), server);
+        client_result.unwrap();
+        server_result.unwrap();
+        assert!(!acceptor.is_connected(&SessionId::new("FIX.4.4", "SERVER", "CLIENT2")));
+        let sessions = acceptor.controls().list();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(
+            sessions[0].0,
+            SessionId::new("FIX.4.4", "SERVER", "CLIENT2")
+        );
+        assert!(sessions[0].1.state.is_disconnected());
+        assert_eq!(sessions[0].1.seq_numbers.next_inbound(), 4);
+    }
+
+    #[tokio::test]
+    async fn refuses_unknown_and_duplicate_sessions() {
+        let acceptor = acceptor();
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (mut client, _client_handle) = initiator("STRANGER");
+        let (client_result, server_result) = tokio::join!(
+            client.run(client_io.compat()),
+            acceptor.accept(server_io.compat())
+        );
+        assert!(matches!(
+            server_result,
+            Err(AcceptorError::UnknownSession(_))
+        ));
+        assert!(client_result.is_err());
+
+        let (first_io, first_server_io) = tokio::io::duplex(64 * 1024);
+        let (mut first, _first_handle) = initiator("CLIENT1");
+        let first_accept = async {
+            let accepted = acceptor.accept(first_server_io.compat()).await;
+            // Keep the first session connected while the second one logs on.
+            let (second_io, second_server_io) = tokio::io::duplex(64 * 1024);
+            let (mut second, _second_handle) = initiator("CLIENT1");
+            let (_, second_result) = tokio::join!(
+                second.run(second_io.compat()),
+                acceptor.accept(second_server_io.compat())
+            );
+            assert!(matches!(
+                second_result,
+                Err(AcceptorError::AlreadyConnected(_))
+            ));
+            drop(accepted);
+        };
+        let (_, ()) = tokio::join!(first.run(first_io.compat()), first_accept);
+        assert!(!acceptor.is_connected(&SessionId::new("FIX.4.4", "SERVER", "CLIENT1")));
+    }
+
+    #[test]
+    fn sub_ids_share_the_session_they_fall_back_to() {
+        let acceptor = acceptor();
+        let desk = |desk| SessionId::new("FIX.4.4", "SERVER", "CLIENT1").with_target_sub_id(desk);
+        let (guard, _handle) = acceptor.check_out(&desk("DESK1")).unwrap();
+        assert!(acceptor.is_connected(&SessionId::new("FIX.4.4", "SERVER", "CLIENT1")));
+        assert!(matches!(
+            acceptor.check_out(&desk("DESK2")),
+            Err(AcceptorError::AlreadyConnected(_))
+        ));
+        drop(guard);
+        assert!(!acceptor.is_connected(&desk("DESK2")));
+        assert!(acceptor.check_out(&desk("DESK2")).is_ok());
+    }
+
+    /// Runs one connection of `client` to `acceptor`, with an order.
+    async fn connect_once(
+        acceptor: &Acceptor<MemoryBackend, Config, NoOpVerifier>,
+        client: &mut Session<MemoryBackend, Config, NoOpVerifier>,
+        client_handle: &SessionHandle,
+    ) {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let server = async {
+            let (accepted, mut handle) = acceptor.accept(server_io.compat()).await.unwrap();
+            let run = accepted.run();
+            let app = async {
+                handle.recv().await.unwrap();
+                client_handle.logout("").unwrap();
+            };
+            let (result, ()) = futures::join!(run, app);
+            result
+        };
+        client_handle.send(b"D", &order("ORDER")).unwrap();
+        let (client_result, server_result) = tokio::join!(client.run(client_io.compat()), server);
+        client_result.unwrap();
+        server_result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reconnections_resume_the_session() {
+        let acceptor = acceptor();
+        let id = SessionId::new("FIX.4.4", "SERVER", "CLIENT1");
+        let (mut client, client_handle) = initiator("CLIENT1");
+        connect_once(&acceptor, &mut client, &client_handle).await;
+        connect_once(&acceptor, &mut client, &client_handle).await;
+        // Logon <A>, NewOrderSingle <D> and Logout <5>, twice.
+        let control = acceptor.controls().get(&id).unwrap();
+        assert_eq!(control.status().seq_numbers.next_inbound(), 7);
+
+        control.disable("maintenance").unwrap();
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let server = async {
+            let (accepted, _handle) = acceptor.accept(server_io.compat()).await.unwrap();
+            accepted.run().await
+        };
+        let (_, server_result) = tokio::join!(client.run(client_io.compat()), server);
+        assert!(matches!(server_result, Err(SessionError::Disabled)));
+        assert!(!control.status().enabled);
+    }
+
+    #[tokio::test]
+    async fn session_setup_runs_once_per_session() {
+        let mut acceptor = acceptor();
+        let setups = Arc::new(Mutex::new(Vec::new()));
+        let recorded = setups.clone();
+        acceptor.set_session_setup(move |id, session| {
+            session
+                .connection_mut()
+                .set_throttle(crate::session::Throttle::new(
+                    crate::session::ThrottleAction::Queue,
+                ));
+            recorded.lock().unwrap().push(id.clone());
+        });
+        let (mut client, client_handle) = initiator("CLIENT2");
+        connect_once(&acceptor, &mut client, &client_handle).await;
+        connect_once(&acceptor, &mut client, &client_handle).await;
+        assert_eq!(
+            *setups.lock().unwrap(),
+            [SessionId::new("FIX.4.4", "SERVER", "CLIENT2")]
+        );
+    }
+
+    #[tokio::test]
+    async fn logon_timeout() {
+        let mut acceptor = acceptor();
+        acceptor.set_logon_timeout(Duration::from_millis(50));
+        let (_client_io, server_io) = tokio::io::duplex(1024);
+        let result = acceptor.accept(server_io.compat()).await;
+        assert!(matches!(result, Err(AcceptorError::LogonTimeout)));
+    }
+
+    #[tokio::test]
+    async fn serve_spawns_a_task_per_session() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sessions_tx, mut sessions_rx) = futures::channel::mpsc::unbounded();
+        tokio::spawn(acceptor().serve(listener, sessions_tx));
+
+        let mut clients = Vec::new();
+        for sender in ["CLIENT1", "CLIENT2"] {
+            let (mut client, handle) = initiator(sender);
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            clients.push((
+                tokio::spawn(async move { client.run_tcp(stream).await }),
+                handle,
+            ));
+        }
+        let mut ids = Vec::new();
+        let mut server_handles = Vec::new();
+        for _ in 0..2 {
+            let (id, handle) = futures::StreamExt::next(&mut sessions_rx).await.unwrap();
+            ids.push(id.target_comp_id().to_string());
+            server_handles.push(handle);
+        }
+        ids.sort();
+        assert_eq!(ids, ["CLIENT1", "CLIENT2"]);
+        for (task, handle) in clients {
+            handle.logout("").unwrap();
+            task.await.unwrap().unwrap();
+        }
+    }
+}
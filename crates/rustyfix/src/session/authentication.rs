@@ -0,0 +1,188 @@
+use crate::SetField;
+use crate::tags::{NEW_PASSWORD, PASSWORD, USERNAME};
+use crate::tagvalue::{EncoderHandle, Message};
+use std::fmt;
+use std::sync::Arc;
+
+/// The `Text <58>` of every rejected Logon, so that it doesn't reveal which
+/// credential was wrong.
+const INVALID_CREDENTIALS: &str = "Invalid Username <553> or Password <554>";
+
+/// Logon authentication hooks of a [`FixConnection`](super::FixConnection).
+///
+/// Acceptors consult [`Authenticate::authenticate`] on every `Logon <A>` that
+/// opens a session; initiators use [`Authenticate::on_outbound_logon`] to add
+/// credentials to the `Logon <A>` they send. Any closure with the signature of
+/// [`Authenticate::authenticate`] is an acceptor-only implementation.
+///
+/// Implementors are shared between sessions through an
+/// [`Arc`](std::sync::Arc), so they only get `&self`.
+pub trait Authenticate: Send + Sync {
+    /// Inspects the counterparty's `Logon <A>`, e.g. its `Username <553>`,
+    /// `Password <554>`, `NewPassword <925>`, `RawData <96>` or custom fields.
+    /// An error rejects the Logon with a `Logout <5>` whose `Text <58>` is
+    /// the error. Accepts everything by default.
+    fn authenticate(&self, logon: &Message<&[u8]>) -> Result<(), String> {
+        let _ = logon;
+        Ok(())
+    }
+
+    /// Adds fields to the `Logon <A>` that initiates the session, after the
+    /// standard header, `EncryptMethod <98>` and `HeartBtInt <108>`. Does
+    /// nothing by default.
+    fn on_outbound_logon(&self, logon: &mut EncoderHandle<Vec<u8>>) {
+        let _ = logon;
+    }
+}
+
+impl<F> Authenticate for F
+where
+    F: Fn(&Message<&[u8]>) -> Result<(), String> + Send + Sync,
+{
+    fn authenticate(&self, logon: &Message<&[u8]>) -> Result<(), String> {
+        self(logon)
+    }
+}
+
+/// A shared [`Authenticate`] implementation, with a [`Debug`](fmt::Debug)
+/// implementation for the structs that hold it.
+#[derive(Clone)]
+pub(crate) struct Authenticator(pub(crate) Arc<dyn Authenticate>);
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Authenticator")
+    }
+}
+
+/// `Username <553>` and `Password <554>` authentication.
+///
+/// Initiators send these credentials, plus `NewPassword <925>` if set, with
+/// their `Logon <A>`. Acceptors require a `Logon <A>` with the same username
+/// and password, compared in constant time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// The `Username <553>`.
+    pub username: String,
+    /// The `Password <554>`.
+    pub password: String,
+    /// The `NewPassword <925>` to switch to, if any. Ignored by acceptors.
+    pub new_password: Option<String>,
+}
+
+impl Credentials {
+    /// Creates new [`Credentials`] without a `NewPassword <925>`.
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            new_password: None,
+        }
+    }
+}
+
+impl Authenticate for Credentials {
+    fn authenticate(&self, logon: &Message<&[u8]>) -> Result<(), String> {
+        let field = |tag| logon.get_raw(tag).unwrap_or_default();
+        // Both are always compared, so timing doesn't reveal which was wrong.
+        let username = constant_time_eq(field(USERNAME), self.username.as_bytes());
+        let password = constant_time_eq(field(PASSWORD), self.password.as_bytes());
+        if username & password {
+            Ok(())
+        } else {
+            Err(INVALID_CREDENTIALS.to_string())
+        }
+    }
+
+    fn on_outbound_logon(&self, logon: &mut EncoderHandle<Vec<u8>>) {
+        logon.set(USERNAME, self.username.as_str());
+        logon.set(PASSWORD, self.password.as_str());
+        if let Some(new_password) = &self.new_password {
+            logon.set(NEW_PASSWORD, new_password.as_str());
+        }
+    }
+}
+
+/// Compares `actual` with `expected` in a time that only depends on the
+/// length of `expected`.
+fn constant_time_eq(actual: &[u8], expected: &[u8]) -> bool {
+    let mut diff = actual.len() ^ expected.len();
+    for (i, byte) in expected.iter().enumerate() {
+        let actual = actual.get(i).copied().unwrap_or_default();
+        diff |= usize::from(std::hint::black_box(actual ^ byte));
+    }
+    diff == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Dictionary;
+    use crate::session::MessageBuilder;
+    use crate::session::backends::MemoryBackend;
+    use crate::tagvalue::Decoder;
+
+    fn logon(fields: &[(u32, &str)]) -> Vec<u8> {
+        let mut builder = MessageBuilder::new();
+        let (mut msg, _) = builder
+            .start_message(b"FIX.4.4", b"A")
+            .header(&MemoryBackend::new("CLIENT", "SERVER"), 1)
+            .get();
+        for (tag, value) in fields {
+            msg.set(*tag, *value);
+        }
+        msg.done();
+        builder.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn credentials_are_added_to_outbound_logons() {
+        let credentials = Credentials {
+            new_password: Some("NEW".to_string()),
+            ..Credentials::new("USER", "PASS")
+        };
+        let mut builder = MessageBuilder::new();
+        let (mut msg, _) = builder.start_message(b"FIX.4.4", b"A").get();
+        credentials.on_outbound_logon(&mut msg);
+        msg.done();
+        let mut decoder = Decoder::new(Dictionary::fix44().unwrap());
+        let message = decoder.decode(builder.as_bytes()).unwrap();
+        assert_eq!(message.get_raw(USERNAME), Some(&b"USER"[..]));
+        assert_eq!(message.get_raw(PASSWORD), Some(&b"PASS"[..]));
+        assert_eq!(message.get_raw(NEW_PASSWORD), Some(&b"NEW"[..]));
+    }
+
+    #[test]
+    fn credentials_check_username_and_password() {
+        let credentials = Credentials::new("USER", "PASS");
+        let mut decoder = Decoder::new(Dictionary::fix44().unwrap());
+        let valid = logon(&[(USERNAME, "USER"), (PASSWORD, "PASS")]);
+        assert!(
+            credentials
+                .authenticate(&decoder.decode(valid.as_slice()).unwrap())
+                .is_ok()
+        );
+        let wrong_password = logon(&[(USERNAME, "USER"), (PASSWORD, "NOPE")]);
+        assert_eq!(
+            credentials.authenticate(&decoder.decode(wrong_password.as_slice()).unwrap()),
+            Err(INVALID_CREDENTIALS.to_string())
+        );
+        let wrong_username = logon(&[(USERNAME, "ADMIN"), (PASSWORD, "PASS")]);
+        assert_eq!(
+            credentials.authenticate(&decoder.decode(wrong_username.as_slice()).unwrap()),
+            Err(INVALID_CREDENTIALS.to_string())
+        );
+        let prefix = logon(&[(USERNAME, "USER"), (PASSWORD, "PAS")]);
+        assert!(
+            credentials
+                .authenticate(&decoder.decode(prefix.as_slice()).unwrap())
+                .is_err()
+        );
+        let anonymous = logon(&[]);
+        assert!(
+            credentials
+                .authenticate(&decoder.decode(anonymous.as_slice()).unwrap())
+                .is_err()
+        );
+    }
+}
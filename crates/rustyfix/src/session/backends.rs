@@ -1,19 +1,24 @@
 //! Session backend implementations for different storage strategies.
 //!
 //! This module provides multiple backend implementations for FIX session management:
-//! - Memory: Fast in-memory storage using high-performance data structures
-//! - File: Persistent file-based storage for reliability across restarts
+//! - Memory: Fast in-memory storage with bounded retention, optionally
+//!   spilling evicted messages to disk
+//! - File: Crash-safe append-only journal for reliability across restarts
 //! - Database: SQLite-based storage for complex querying and durability
+//!   (requires the `utils-rusqlite` feature)
 
 use crate::FieldType;
-use crate::session::{Backend, Environment};
+use crate::session::journal::{FsyncPolicy, Journal, JournalRecord, SeqNumFile, SessionFile};
+use crate::session::{Backend, Clock, Environment, SeqNumbers, SystemClock};
 use log;
-use rustc_hash::FxHashMap;
+use quanta::Instant;
 use smallvec::SmallVec;
 use smartstring::alias::String as SmartString;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
 /// Backend implementation errors
@@ -39,6 +44,17 @@ pub enum BackendError {
     /// Storage capacity has been exceeded
     #[error("Storage capacity exceeded")]
     CapacityExceeded,
+    /// The embedded database reported an error. Always present, so that
+    /// enabling `utils-rusqlite` doesn't break exhaustive matches.
+    #[error("Database error: {0}")]
+    Database(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[cfg(feature = "utils-rusqlite")]
+impl From<rusqlite::Error> for BackendError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Database(Box::new(err))
+    }
 }
 
 impl<'a> FieldType<'a> for BackendError {
@@ -62,6 +78,52 @@ impl<'a> FieldType<'a> for BackendError {
     }
 }
 
+/// Retention limits of a [`MemoryBackend`], applied to inbound and outbound
+/// messages separately.
+///
+/// Whenever a message is stored, the messages with the lowest sequence
+/// numbers are evicted until every limit is respected again. Evicted messages
+/// are spilled to [`MemoryRetention::spill_path`], if set, or dropped. Either
+/// way, `ResendRequest <2>`s for them are still answered: with the spilled
+/// copies, or with a `SequenceReset-GapFill <4>`.
+#[derive(Debug, Clone)]
+pub struct MemoryRetention {
+    /// Maximum number of messages.
+    pub max_messages: usize,
+    /// Maximum total size of the messages, in bytes, if any.
+    pub max_bytes: Option<usize>,
+    /// Maximum time a message is kept, if any.
+    pub max_age: Option<Duration>,
+    /// The [`Journal`] that evicted messages are appended to, if any. It's
+    /// emptied when the backend is created, so it only ever holds messages of
+    /// the current process. Clones of the backend share it, like clones of a
+    /// [`FileBackend`] share their journal.
+    pub spill_path: Option<PathBuf>,
+    /// How many spilled messages per direction are kept, if limited. The
+    /// journal is compacted down to this many whenever twice as many were
+    /// spilled since the last compaction. 100 000 by default.
+    pub max_spilled_messages: Option<usize>,
+}
+
+impl Default for MemoryRetention {
+    fn default() -> Self {
+        Self {
+            max_messages: 10_000,
+            max_bytes: None,
+            max_age: None,
+            spill_path: None,
+            max_spilled_messages: Some(100_000),
+        }
+    }
+}
+
+/// A message kept by a [`MemoryBackend`].
+#[derive(Debug, Clone)]
+struct StoredMessage {
+    bytes: SmallVec<[u8; 1024]>,
+    stored_at: Instant,
+}
+
 /// High-performance in-memory backend using optimized data structures
 #[derive(Debug, Clone)]
 pub struct MemoryBackend {
@@ -69,13 +131,22 @@ pub struct MemoryBackend {
     target_comp_id: SmartString,
     message_encoding: Option<SmartString>,
     /// Outbound message store for resend requests
-    outbound_messages: FxHashMap<u64, SmallVec<[u8; 1024]>>,
+    outbound_messages: BTreeMap<u64, StoredMessage>,
     /// Inbound message store for duplicate detection
-    inbound_messages: FxHashMap<u64, SmallVec<[u8; 1024]>>,
+    inbound_messages: BTreeMap<u64, StoredMessage>,
+    /// Total size of `outbound_messages`, in bytes
+    outbound_bytes: usize,
+    /// Total size of `inbound_messages`, in bytes
+    inbound_bytes: usize,
     /// Queue of pending outbound messages
     pending_queue: VecDeque<SmallVec<[u8; 1024]>>,
-    /// Maximum messages to store (prevents memory growth)
-    max_stored_messages: usize,
+    /// The message most recently returned by `pending_message`
+    current_pending: Option<SmallVec<[u8; 1024]>>,
+    /// Limits that prevent memory growth
+    retention: MemoryRetention,
+    /// Where evicted messages go
+    spill: Option<Arc<Mutex<Journal>>>,
+    clock: Arc<dyn Clock>,
     /// Environment setting
     environment: Environment,
 }
@@ -86,16 +157,12 @@ impl MemoryBackend {
         sender_comp_id: impl Into<SmartString>,
         target_comp_id: impl Into<SmartString>,
     ) -> Self {
-        Self {
-            sender_comp_id: sender_comp_id.into(),
-            target_comp_id: target_comp_id.into(),
-            message_encoding: None,
-            outbound_messages: FxHashMap::default(),
-            inbound_messages: FxHashMap::default(),
-            pending_queue: VecDeque::new(),
-            max_stored_messages: 10000, // Configurable limit
-            environment: Environment::Production { allow_test: false },
-        }
+        Self::with_config(
+            sender_comp_id,
+            target_comp_id,
+            MemoryRetention::default().max_messages,
+            Environment::Production { allow_test: false },
+        )
     }
 
     /// Creates a new memory backend with custom configuration
@@ -109,31 +176,71 @@ impl MemoryBackend {
             sender_comp_id: sender_comp_id.into(),
             target_comp_id: target_comp_id.into(),
             message_encoding: None,
-            outbound_messages: FxHashMap::default(),
-            inbound_messages: FxHashMap::default(),
+            outbound_messages: BTreeMap::new(),
+            inbound_messages: BTreeMap::new(),
+            outbound_bytes: 0,
+            inbound_bytes: 0,
             pending_queue: VecDeque::new(),
-            max_stored_messages,
+            current_pending: None,
+            retention: MemoryRetention {
+                max_messages: max_stored_messages,
+                ..MemoryRetention::default()
+            },
+            spill: None,
+            clock: Arc::new(SystemClock),
             environment,
         }
     }
 
+    /// Creates a new memory backend with the given [`MemoryRetention`],
+    /// replacing any previous spill journal.
+    pub fn with_retention(
+        sender_comp_id: impl Into<SmartString>,
+        target_comp_id: impl Into<SmartString>,
+        retention: MemoryRetention,
+    ) -> Result<Self, BackendError> {
+        let mut backend = Self::new(sender_comp_id, target_comp_id);
+        if let Some(path) = &retention.spill_path {
+            // Spilled messages are a cache, not a durable store, so whatever
+            // a previous run left behind is discarded unread.
+            match std::fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+            let (journal, _) = Journal::open(path, FsyncPolicy::Never)?;
+            backend.spill = Some(Arc::new(Mutex::new(journal)));
+        }
+        backend.retention = retention;
+        Ok(backend)
+    }
+
+    /// Returns the [`MemoryRetention`] in use.
+    pub fn retention(&self) -> &MemoryRetention {
+        &self.retention
+    }
+
+    /// Reads the time from `clock` to enforce [`MemoryRetention::max_age`].
+    /// [`SystemClock`] by default.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Returns the number of messages and bytes currently kept in memory for
+    /// `direction`.
+    pub fn stored(&self, direction: Direction) -> (usize, usize) {
+        match direction {
+            Direction::Inbound => (self.inbound_messages.len(), self.inbound_bytes),
+            Direction::Outbound => (self.outbound_messages.len(), self.outbound_bytes),
+        }
+    }
+
     /// Store an outbound message for potential resend
     pub fn store_outbound_message(
         &mut self,
         seq_num: u64,
         message: &[u8],
     ) -> Result<(), BackendError> {
-        if self.outbound_messages.len() >= self.max_stored_messages {
-            // ✅ CRITICAL FIX: Use count-based retention instead of sequence-based to handle non-contiguous sequence numbers
-            // FIX protocol allows gaps in sequence numbers (e.g., 1000, 1005, 1010), so we cannot assume contiguity
-            let target_size = self.max_stored_messages * 3 / 4; // Reduce to 75% capacity
-            self.cleanup_outbound_messages(target_size);
-        }
-
-        let mut buffer = SmallVec::new();
-        buffer.extend_from_slice(message);
-        self.outbound_messages.insert(seq_num, buffer);
-        Ok(())
+        self.store(Direction::Outbound, seq_num, message)
     }
 
     /// Store an inbound message for duplicate detection
@@ -142,67 +249,81 @@ impl MemoryBackend {
         seq_num: u64,
         message: &[u8],
     ) -> Result<(), BackendError> {
-        if self.inbound_messages.len() >= self.max_stored_messages {
-            // ✅ CRITICAL FIX: Use count-based retention instead of sequence-based to handle non-contiguous sequence numbers
-            // FIX protocol allows gaps in sequence numbers (e.g., 1000, 1005, 1010), so we cannot assume contiguity
-            let target_size = self.max_stored_messages * 3 / 4; // Reduce to 75% capacity
-            self.cleanup_inbound_messages(target_size);
-        }
-
-        let mut buffer = SmallVec::new();
-        buffer.extend_from_slice(message);
-        self.inbound_messages.insert(seq_num, buffer);
-        Ok(())
+        self.store(Direction::Inbound, seq_num, message)
     }
 
-    /// Clean up outbound message store using count-based retention (handles non-contiguous sequence numbers)
-    fn cleanup_outbound_messages(&mut self, target_size: usize) {
-        if self.outbound_messages.len() <= target_size {
-            return;
+    /// Stores `message`, then evicts the oldest messages of `direction` that
+    /// exceed the [`MemoryRetention`] limits. Sequence numbers may have gaps,
+    /// so "oldest" means lowest sequence number.
+    fn store(
+        &mut self,
+        direction: Direction,
+        seq_num: u64,
+        message: &[u8],
+    ) -> Result<(), BackendError> {
+        let now = self.clock.now();
+        let (messages, bytes) = match direction {
+            Direction::Inbound => (&mut self.inbound_messages, &mut self.inbound_bytes),
+            Direction::Outbound => (&mut self.outbound_messages, &mut self.outbound_bytes),
+        };
+        let stored = StoredMessage {
+            bytes: SmallVec::from_slice(message),
+            stored_at: now,
+        };
+        if let Some(replaced) = messages.insert(seq_num, stored) {
+            *bytes -= replaced.bytes.len();
         }
-
-        // Collect and sort sequence numbers to find the oldest messages
-        let mut seq_nums: SmallVec<[u64; 32]> = self.outbound_messages.keys().cloned().collect();
-        seq_nums.sort_unstable();
-
-        // Calculate how many messages to remove
-        let messages_to_remove = self.outbound_messages.len() - target_size;
-
-        // Remove the oldest messages by sequence number (regardless of gaps)
-        for &seq_num in seq_nums.iter().take(messages_to_remove) {
-            self.outbound_messages.remove(&seq_num);
+        *bytes += message.len();
+
+        let retention = &self.retention;
+        let mut evicted = Vec::new();
+        while let Some((_, oldest)) = messages.first_key_value() {
+            let expired = retention
+                .max_age
+                .is_some_and(|max_age| now.duration_since(oldest.stored_at) > max_age);
+            let too_large = retention
+                .max_bytes
+                .is_some_and(|max_bytes| *bytes > max_bytes);
+            if messages.len() <= retention.max_messages && !too_large && !expired {
+                break;
+            }
+            if let Some((seq_num, oldest)) = messages.pop_first() {
+                *bytes -= oldest.bytes.len();
+                evicted.push((seq_num, oldest.bytes));
+            }
+        }
+        if evicted.is_empty() {
+            return Ok(());
         }
-
         log::debug!(
-            "Cleaned up outbound message store: removed {} oldest messages, {} messages remaining",
-            messages_to_remove,
-            self.outbound_messages.len()
+            "Evicted {} {} messages, {} remaining",
+            evicted.len(),
+            direction.as_str(),
+            messages.len()
         );
-    }
-
-    /// Clean up inbound message store using count-based retention (handles non-contiguous sequence numbers)
-    fn cleanup_inbound_messages(&mut self, target_size: usize) {
-        if self.inbound_messages.len() <= target_size {
-            return;
+        let Some(mut spill) = self.spill()? else {
+            return Ok(());
+        };
+        for (seq_num, message) in evicted {
+            spill.append(seq_num, direction, &message)?;
         }
-
-        // Collect and sort sequence numbers to find the oldest messages
-        let mut seq_nums: SmallVec<[u64; 32]> = self.inbound_messages.keys().cloned().collect();
-        seq_nums.sort_unstable();
-
-        // Calculate how many messages to remove
-        let messages_to_remove = self.inbound_messages.len() - target_size;
-
-        // Remove the oldest messages by sequence number (regardless of gaps)
-        for &seq_num in seq_nums.iter().take(messages_to_remove) {
-            self.inbound_messages.remove(&seq_num);
+        if let Some(max_spilled) = self.retention.max_spilled_messages
+            && spill.appended_since_compaction() >= 2 * max_spilled as u64
+        {
+            spill.compact(max_spilled)?;
         }
+        Ok(())
+    }
 
-        log::debug!(
-            "Cleaned up inbound message store: removed {} oldest messages, {} messages remaining",
-            messages_to_remove,
-            self.inbound_messages.len()
-        );
+    fn spill(&self) -> Result<Option<MutexGuard<'_, Journal>>, BackendError> {
+        self.spill
+            .as_ref()
+            .map(|spill| {
+                spill.lock().map_err(|_| {
+                    BackendError::Io(std::io::Error::other("spill journal lock poisoned"))
+                })
+            })
+            .transpose()
     }
 
     /// Queue a message for sending
@@ -214,13 +335,19 @@ impl MemoryBackend {
 
     /// Get messages for resend request
     pub fn get_messages_for_resend(&self, range: Range<u64>) -> SmallVec<[&[u8]; 16]> {
-        let mut messages = SmallVec::new();
-        for seq_num in range {
-            if let Some(message) = self.outbound_messages.get(&seq_num) {
-                messages.push(message.as_slice());
-            }
+        self.outbound_messages
+            .range(range)
+            .map(|(_, message)| message.bytes.as_slice())
+            .collect()
+    }
+
+    /// Returns the spilled outbound messages in `range`, sorted by sequence
+    /// number.
+    pub fn spilled_messages(&self, range: Range<u64>) -> Result<Vec<JournalRecord>, BackendError> {
+        match self.spill()? {
+            Some(spill) => spill.messages_in_range(Direction::Outbound, range),
+            None => Ok(Vec::new()),
         }
-        messages
     }
 
     /// Check if a message is a duplicate
@@ -268,14 +395,14 @@ impl Backend for MemoryBackend {
 
     fn on_outbound_message(&mut self, message: &[u8]) -> Result<(), Self::Error> {
         log::debug!("Memory backend: processing outbound message");
-        // Parse sequence number and store for potential resend
-        // This is a simplified implementation - in practice you'd parse the actual message
-        // For now, we'll queue it for sending
-        self.queue_message(message);
-        Ok(())
+        let Some(seq_num) = msg_seq_num(message) else {
+            log::warn!("Memory backend: outbound message without MsgSeqNum(34), not stored");
+            return Ok(());
+        };
+        self.store_outbound_message(seq_num, message)
     }
 
-    fn on_resend_request(&mut self, range: Range<u64>) -> Result<(), Self::Error> {
+    fn on_resend_request(&mut self, range: Range<u64>) -> Result<Vec<Vec<u8>>, Self::Error> {
         log::info!("Memory backend: processing resend request for range {range:?}");
 
         if range.start > range.end {
@@ -285,23 +412,26 @@ impl Backend for MemoryBackend {
             });
         }
 
-        let messages: SmallVec<[SmallVec<[u8; 1024]>; 16]> = self
-            .get_messages_for_resend(range.clone())
+        // Spilled messages are older than the ones in memory, but copies in
+        // memory win just in case.
+        let mut messages: BTreeMap<u64, SmallVec<[u8; 1024]>> = self
+            .spilled_messages(range.clone())?
             .into_iter()
-            .map(SmallVec::from)
+            .map(|record| (record.seq_num, SmallVec::from_vec(record.message)))
             .collect();
+        for (seq_num, message) in self.outbound_messages.range(range.clone()) {
+            messages.insert(*seq_num, message.bytes.clone());
+        }
         log::info!(
             "Found {} messages for resend in range {:?}",
             messages.len(),
             range
         );
 
-        // Re-queue messages for transmission
-        for message in messages {
-            self.queue_message(&message);
-        }
-
-        Ok(())
+        Ok(messages
+            .into_values()
+            .map(|message| message.to_vec())
+            .collect())
     }
 
     fn on_successful_handshake(&mut self) -> Result<(), Self::Error> {
@@ -316,27 +446,97 @@ impl Backend for MemoryBackend {
     }
 
     fn pending_message(&mut self) -> Option<&[u8]> {
-        self.pending_queue.front().map(|v| v.as_slice())
+        self.current_pending = self.pending_queue.pop_front();
+        self.current_pending.as_deref()
+    }
+
+    fn on_sequence_reset(&mut self) -> Result<(), Self::Error> {
+        self.outbound_messages.clear();
+        self.inbound_messages.clear();
+        self.outbound_bytes = 0;
+        self.inbound_bytes = 0;
+        self.pending_queue.clear();
+        if let Some(mut spill) = self.spill()? {
+            spill.clear()?;
+        }
+        Ok(())
     }
 }
 
-/// File-based backend for persistent storage
+/// Tuning options for a [`FileBackend`].
+#[derive(Debug, Clone)]
+pub struct FileBackendConfig {
+    /// When journal writes are flushed to stable storage.
+    pub fsync_policy: FsyncPolicy,
+    /// Compacts the journal once this many records have been appended since
+    /// the last compaction. `None` disables automatic compaction.
+    pub compaction_threshold: Option<u64>,
+    /// How many of the most recent messages per direction survive compaction.
+    pub retained_messages: usize,
+}
+
+impl Default for FileBackendConfig {
+    fn default() -> Self {
+        Self {
+            fsync_policy: FsyncPolicy::EveryMessage,
+            compaction_threshold: Some(100_000),
+            retained_messages: 10_000,
+        }
+    }
+}
+
+/// File-based backend for persistent storage.
+///
+/// Every inbound and outbound message is appended to a [`Journal`] at
+/// `storage_path` as soon as the session sees it, and the next expected
+/// sequence numbers are kept in a QuickFIX-style [`SeqNumFile`] next to it
+/// (`<storage_path>.seqnums`). Nothing is lost between explicit saves;
+/// durability against power loss is governed by
+/// [`FileBackendConfig::fsync_policy`].
 #[derive(Debug, Clone)]
 pub struct FileBackend {
     sender_comp_id: SmartString,
     target_comp_id: SmartString,
     message_encoding: Option<SmartString>,
     storage_path: PathBuf,
+    config: FileBackendConfig,
+    store: Arc<Mutex<FileStore>>,
     /// In-memory cache for performance
     memory_cache: MemoryBackend,
 }
 
+#[derive(Debug)]
+struct FileStore {
+    journal: Journal,
+    seq_num_file: SeqNumFile,
+    seq_numbers: SeqNumbers,
+    session_file: SessionFile,
+    creation_time: SystemTime,
+}
+
 impl FileBackend {
-    /// Creates a new file-based backend
+    /// Creates a new file-based backend with the default [`FileBackendConfig`],
+    /// recovering any state previously stored at `storage_path`.
     pub fn new(
         sender_comp_id: impl Into<SmartString>,
         target_comp_id: impl Into<SmartString>,
         storage_path: impl Into<PathBuf>,
+    ) -> Result<Self, BackendError> {
+        Self::with_config(
+            sender_comp_id,
+            target_comp_id,
+            storage_path,
+            FileBackendConfig::default(),
+        )
+    }
+
+    /// Creates a new file-based backend with custom configuration, recovering
+    /// any state previously stored at `storage_path`.
+    pub fn with_config(
+        sender_comp_id: impl Into<SmartString>,
+        target_comp_id: impl Into<SmartString>,
+        storage_path: impl Into<PathBuf>,
+        config: FileBackendConfig,
     ) -> Result<Self, BackendError> {
         let sender = sender_comp_id.into();
         let target = target_comp_id.into();
@@ -347,45 +547,171 @@ impl FileBackend {
             std::fs::create_dir_all(parent)?;
         }
 
-        Ok(Self {
+        let (journal, records) = Journal::open(&path, config.fsync_policy)?;
+        let mut seq_num_path = path.clone().into_os_string();
+        seq_num_path.push(".seqnums");
+        let seq_num_file = SeqNumFile::new(seq_num_path);
+
+        // Sequence numbers are written after the journal record, so the
+        // journal may be ahead of the sequence number file after a crash.
+        // Going with the higher value is always safe: the counterparty will
+        // ask for a resend (or we will gap fill), whereas reusing a sequence
+        // number would break the session.
+        let stored = seq_num_file.load()?.unwrap_or_default();
+        let mut seq_numbers = stored;
+        for record in &records {
+            bump_seq_numbers(&mut seq_numbers, record.seq_num, record.direction);
+        }
+        seq_num_file.store(seq_numbers, config.fsync_policy != FsyncPolicy::Never)?;
+
+        let mut session_path = path.clone().into_os_string();
+        session_path.push(".session");
+        let session_file = SessionFile::new(session_path);
+        let creation_time = match session_file.load()? {
+            Some(creation_time) => creation_time,
+            None => session_file.store(SystemTime::now())?,
+        };
+
+        let mut backend = Self {
             sender_comp_id: sender.clone(),
             target_comp_id: target.clone(),
             message_encoding: None,
             storage_path: path,
+            config,
+            store: Arc::new(Mutex::new(FileStore {
+                journal,
+                seq_num_file,
+                seq_numbers,
+                session_file,
+                creation_time,
+            })),
             memory_cache: MemoryBackend::new(sender, target),
-        })
+        };
+        backend.cache_records(records)?;
+        Ok(backend)
     }
 
-    /// Load messages from file into memory cache
-    pub fn load_from_disk(&mut self) -> Result<(), BackendError> {
-        if !self.storage_path.exists() {
-            return Ok(());
-        }
+    fn store(&self) -> Result<MutexGuard<'_, FileStore>, BackendError> {
+        self.store
+            .lock()
+            .map_err(|_| BackendError::Io(std::io::Error::other("journal lock poisoned")))
+    }
 
-        let contents = std::fs::read_to_string(&self.storage_path)?;
-        for line in contents.lines() {
-            if let Some((seq_str, message)) = line.split_once(':')
-                && let Ok(seq_num) = seq_str.parse::<u64>()
-            {
-                let message_bytes = message.as_bytes();
-                self.memory_cache
-                    .store_outbound_message(seq_num, message_bytes)?;
+    fn cache_records(&mut self, records: Vec<JournalRecord>) -> Result<(), BackendError> {
+        for record in records {
+            match record.direction {
+                Direction::Inbound => self
+                    .memory_cache
+                    .store_inbound_message(record.seq_num, &record.message)?,
+                Direction::Outbound => self
+                    .memory_cache
+                    .store_outbound_message(record.seq_num, &record.message)?,
             }
         }
         Ok(())
     }
 
-    /// Persist messages to disk
+    /// Returns the path of the message journal.
+    pub fn journal_path(&self) -> &std::path::Path {
+        &self.storage_path
+    }
+
+    /// Returns the [`FileBackendConfig`] in use.
+    pub fn config(&self) -> &FileBackendConfig {
+        &self.config
+    }
+
+    /// Reloads the in-memory cache from the journal.
+    pub fn load_from_disk(&mut self) -> Result<(), BackendError> {
+        let records = self.store()?.journal.records()?;
+        self.cache_records(records)
+    }
+
+    /// Forces all journal records and sequence numbers to stable storage,
+    /// regardless of the [`FsyncPolicy`]. Messages are written as they are
+    /// processed, so this is only needed to close a
+    /// [`FsyncPolicy::Batched`] or [`FsyncPolicy::Never`] window early.
     pub fn save_to_disk(&self) -> Result<(), BackendError> {
-        use std::io::Write;
+        let mut store = self.store()?;
+        store.journal.sync()?;
+        store.seq_num_file.store(store.seq_numbers, true)
+    }
 
-        let mut file = std::fs::File::create(&self.storage_path)?;
-        for (seq_num, message) in &self.memory_cache.outbound_messages {
-            writeln!(file, "{}:{}", seq_num, String::from_utf8_lossy(message))?;
+    /// Appends `message` to the journal and advances the stored sequence
+    /// numbers accordingly.
+    pub fn store_message(
+        &self,
+        seq_num: u64,
+        message: &[u8],
+        direction: Direction,
+    ) -> Result<(), BackendError> {
+        let mut store = self.store()?;
+        store.journal.append(seq_num, direction, message)?;
+        let before = store.seq_numbers;
+        bump_seq_numbers(&mut store.seq_numbers, seq_num, direction);
+        if before.next_inbound != store.seq_numbers.next_inbound
+            || before.next_outbound != store.seq_numbers.next_outbound
+        {
+            let fsync = self.config.fsync_policy == FsyncPolicy::EveryMessage;
+            store.seq_num_file.store(store.seq_numbers, fsync)?;
+        }
+        if let Some(threshold) = self.config.compaction_threshold
+            && store.journal.appended_since_compaction() >= threshold
+        {
+            store.journal.compact(self.config.retained_messages)?;
         }
-        file.sync_all()?;
         Ok(())
     }
+
+    /// Returns the next expected sequence numbers.
+    pub fn seq_numbers(&self) -> Result<SeqNumbers, BackendError> {
+        Ok(self.store()?.seq_numbers)
+    }
+
+    /// Overrides the next expected sequence numbers, e.g. after a
+    /// `SequenceReset <4>`.
+    ///
+    /// Journal records at or above the new values are dropped, as their
+    /// sequence numbers are going to be reused.
+    pub fn set_seq_numbers(&self, seq_numbers: SeqNumbers) -> Result<(), BackendError> {
+        let mut store = self.store()?;
+        store.journal.retain(|record| match record.direction {
+            Direction::Inbound => record.seq_num < seq_numbers.next_inbound,
+            Direction::Outbound => record.seq_num < seq_numbers.next_outbound,
+        })?;
+        store.seq_numbers = seq_numbers;
+        store.seq_num_file.store(seq_numbers, true)
+    }
+
+    /// Compacts the journal right away. See [`Journal::compact`].
+    pub fn compact(&self) -> Result<(), BackendError> {
+        self.store()?.journal.compact(self.config.retained_messages)
+    }
+
+    /// Returns when the store was created or last [reset](Self::reset).
+    pub fn creation_time(&self) -> Result<SystemTime, BackendError> {
+        Ok(self.store()?.creation_time)
+    }
+
+    /// Discards all stored messages and resets both sequence numbers to 1.
+    pub fn reset(&mut self) -> Result<(), BackendError> {
+        {
+            let mut store = self.store()?;
+            store.journal.clear()?;
+            store.seq_numbers = SeqNumbers::default();
+            store.seq_num_file.store(store.seq_numbers, true)?;
+            store.creation_time = store.session_file.store(SystemTime::now())?;
+        }
+        self.memory_cache.on_sequence_reset()
+    }
+}
+
+fn bump_seq_numbers(seq_numbers: &mut SeqNumbers, seq_num: u64, direction: Direction) {
+    let next = match direction {
+        Direction::Inbound => &mut seq_numbers.next_inbound,
+        Direction::Outbound => &mut seq_numbers.next_outbound,
+    };
+    *next = (*next).max(seq_num.saturating_add(1));
 }
 
 impl Backend for FileBackend {
@@ -408,6 +734,8 @@ impl Backend for FileBackend {
     }
 
     fn on_heartbeat_is_due(&mut self) -> Result<(), Self::Error> {
+        // Bounds the age of a batch on otherwise idle sessions.
+        self.store()?.journal.sync_if_due()?;
         self.memory_cache.on_heartbeat_is_due()
     }
 
@@ -415,23 +743,53 @@ impl Backend for FileBackend {
         &mut self,
         message: crate::tagvalue::Message<&[u8]>,
     ) -> Result<(), Self::Error> {
-        let result = self.memory_cache.on_inbound_app_message(message);
-        // Persist periodically (every 100 messages for performance)
-        if self.memory_cache.inbound_messages.len() % 100 == 0 {
-            self.save_to_disk()?;
+        if let Ok(seq_num) = message.get::<u64>(34) {
+            self.store_message(seq_num, message.as_bytes(), Direction::Inbound)?;
+        }
+        self.memory_cache.on_inbound_app_message(message)
+    }
+
+    fn on_inbound_message(
+        &mut self,
+        message: crate::tagvalue::Message<&[u8]>,
+        is_app: bool,
+    ) -> Result<(), Self::Error> {
+        if is_app {
+            return self.on_inbound_app_message(message);
+        }
+        // Session-level messages also advance the inbound sequence number.
+        if let Ok(seq_num) = message.get::<u64>(34) {
+            self.store_message(seq_num, message.as_bytes(), Direction::Inbound)?;
         }
-        result
+        Ok(())
     }
 
     fn on_outbound_message(&mut self, message: &[u8]) -> Result<(), Self::Error> {
-        let result = self.memory_cache.on_outbound_message(message);
-        // Persist outbound messages immediately for reliability
-        self.save_to_disk()?;
-        result
+        let Some(seq_num) = msg_seq_num(message) else {
+            log::warn!("File backend: outbound message without MsgSeqNum(34), not stored");
+            return Ok(());
+        };
+        self.store_message(seq_num, message, Direction::Outbound)?;
+        self.memory_cache.store_outbound_message(seq_num, message)
     }
 
-    fn on_resend_request(&mut self, range: Range<u64>) -> Result<(), Self::Error> {
-        self.memory_cache.on_resend_request(range)
+    fn on_resend_request(&mut self, range: Range<u64>) -> Result<Vec<Vec<u8>>, Self::Error> {
+        log::info!("File backend: processing resend request for range {range:?}");
+
+        if range.start > range.end {
+            return Err(BackendError::InvalidRange {
+                start: range.start,
+                end: range.end,
+            });
+        }
+
+        // The journal also covers messages that were evicted from the cache.
+        let records = self
+            .store()?
+            .journal
+            .messages_in_range(Direction::Outbound, range)?;
+        log::info!("Found {} messages in journal for resend", records.len());
+        Ok(records.into_iter().map(|record| record.message).collect())
     }
 
     fn on_successful_handshake(&mut self) -> Result<(), Self::Error> {
@@ -447,24 +805,86 @@ impl Backend for FileBackend {
     fn pending_message(&mut self) -> Option<&[u8]> {
         self.memory_cache.pending_message()
     }
+
+    fn stored_seq_numbers(&self) -> Option<SeqNumbers> {
+        self.seq_numbers().ok()
+    }
+
+    fn on_seq_numbers_changed(&mut self, seq_numbers: SeqNumbers) -> Result<(), Self::Error> {
+        let mut store = self.store()?;
+        let current = store.seq_numbers;
+        if seq_numbers.next_inbound < current.next_inbound
+            || seq_numbers.next_outbound < current.next_outbound
+        {
+            // Journal records beyond the new values would bump them back up
+            // on the next open.
+            drop(store);
+            return self.set_seq_numbers(seq_numbers);
+        }
+        if seq_numbers.next_inbound != current.next_inbound
+            || seq_numbers.next_outbound != current.next_outbound
+        {
+            store.seq_numbers = seq_numbers;
+            let fsync = self.config.fsync_policy == FsyncPolicy::EveryMessage;
+            store.seq_num_file.store(seq_numbers, fsync)?;
+        }
+        Ok(())
+    }
+
+    fn creation_time(&self) -> Option<SystemTime> {
+        FileBackend::creation_time(self).ok()
+    }
+
+    fn on_sequence_reset(&mut self) -> Result<(), Self::Error> {
+        self.reset()
+    }
+}
+
+/// The direction of a FIX message relative to the local session.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Received from the counterparty.
+    Inbound,
+    /// Sent to the counterparty.
+    Outbound,
+}
+
+impl Direction {
+    /// Returns a lowercase textual representation of `self`, suitable for
+    /// persistent storage.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
 }
 
-/// Database backend for robust persistence and querying
-/// Uses SQLite for simplicity and portability
+/// Database backend for robust persistence and querying.
+///
+/// Messages are stored in an embedded SQLite database, keyed by session ID,
+/// direction and `MsgSeqNum <34>`, together with the session's next expected
+/// sequence numbers and creation time. Outbound messages survive restarts and
+/// can be replayed from disk by [`Backend::on_resend_request`].
+#[cfg(feature = "utils-rusqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "utils-rusqlite")))]
 #[derive(Debug, Clone)]
 pub struct DatabaseBackend {
     sender_comp_id: SmartString,
     target_comp_id: SmartString,
     message_encoding: Option<SmartString>,
+    session_id: SmartString,
     db_path: PathBuf,
+    connection: Arc<Mutex<rusqlite::Connection>>,
     /// In-memory cache for performance
     memory_cache: MemoryBackend,
 }
 
+#[cfg(feature = "utils-rusqlite")]
 impl DatabaseBackend {
-    /// Creates a new database backend
-    /// Note: Full SQLite implementation would require adding the rusqlite dependency
-    /// This is a placeholder implementation that falls back to file storage
+    /// Opens (or creates) the SQLite database at `db_path` and initializes its
+    /// schema. The session ID is derived from the CompIDs, i.e.
+    /// `SENDER->TARGET`.
     pub fn new(
         sender_comp_id: impl Into<SmartString>,
         target_comp_id: impl Into<SmartString>,
@@ -479,50 +899,209 @@ impl DatabaseBackend {
             std::fs::create_dir_all(parent)?;
         }
 
-        Ok(Self {
+        let connection = rusqlite::Connection::open(&path)?;
+        let backend = Self {
             sender_comp_id: sender.clone(),
             target_comp_id: target.clone(),
             message_encoding: None,
+            session_id: format!("{sender}->{target}").into(),
             db_path: path,
+            connection: Arc::new(Mutex::new(connection)),
             memory_cache: MemoryBackend::new(sender, target),
-        })
+        };
+        backend.initialize_schema()?;
+        Ok(backend)
+    }
+
+    /// Returns the session ID under which `self` stores its messages.
+    pub fn session_id(&self) -> &str {
+        self.session_id.as_str()
+    }
+
+    /// Returns the path of the underlying SQLite database.
+    pub fn db_path(&self) -> &std::path::Path {
+        &self.db_path
     }
 
-    /// Initialize database schema
-    /// In a full implementation, this would create the necessary tables
+    fn connection(&self) -> Result<MutexGuard<'_, rusqlite::Connection>, BackendError> {
+        self.connection
+            .lock()
+            .map_err(|_| BackendError::Io(std::io::Error::other("database lock poisoned")))
+    }
+
+    /// Creates the necessary tables if they don't exist yet, and registers
+    /// this session. Calling this more than once is harmless.
     pub fn initialize_schema(&self) -> Result<(), BackendError> {
         log::info!(
             "Database backend: initializing schema at {:?}",
             self.db_path
         );
-        // Placeholder - would execute SQL CREATE TABLE statements
+        let connection = self.connection()?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                session_id    TEXT PRIMARY KEY NOT NULL,
+                creation_time TEXT NOT NULL,
+                next_inbound  INTEGER NOT NULL,
+                next_outbound INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS messages (
+                session_id TEXT NOT NULL,
+                direction  TEXT NOT NULL,
+                seq_num    INTEGER NOT NULL,
+                content    BLOB NOT NULL,
+                stored_at  TEXT NOT NULL,
+                PRIMARY KEY (session_id, direction, seq_num)
+            );",
+        )?;
+        connection.execute(
+            "INSERT OR IGNORE INTO sessions (session_id, creation_time, next_inbound, next_outbound)
+             VALUES (?1, ?2, 1, 1)",
+            rusqlite::params![self.session_id.as_str(), utc_timestamp()],
+        )?;
         Ok(())
     }
 
-    /// Store message in database
-    /// Placeholder implementation - would execute SQL INSERT
+    /// Stores a message in the database and advances the persisted sequence
+    /// number for `direction` past `seq_num`.
     pub fn store_message_in_db(
         &self,
         seq_num: u64,
-        _message: &[u8],
-        direction: &str,
+        message: &[u8],
+        direction: Direction,
     ) -> Result<(), BackendError> {
-        log::debug!("Database backend: storing {direction} message seq_num={seq_num}");
-        // Placeholder for SQL: INSERT INTO messages (seq_num, direction, content, timestamp) VALUES (?, ?, ?, ?)
+        log::debug!(
+            "Database backend: storing {} message seq_num={seq_num}",
+            direction.as_str()
+        );
+        let seq_num = to_sql_seq_num(seq_num)?;
+        let mut connection = self.connection()?;
+        let tx = connection.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO messages (session_id, direction, seq_num, content, stored_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                self.session_id.as_str(),
+                direction.as_str(),
+                seq_num,
+                message,
+                utc_timestamp()
+            ],
+        )?;
+        let update = match direction {
+            Direction::Inbound => {
+                "UPDATE sessions SET next_inbound = MAX(next_inbound, ?2 + 1) WHERE session_id = ?1"
+            }
+            Direction::Outbound => {
+                "UPDATE sessions SET next_outbound = MAX(next_outbound, ?2 + 1) WHERE session_id = ?1"
+            }
+        };
+        tx.execute(update, rusqlite::params![self.session_id.as_str(), seq_num])?;
+        tx.commit()?;
         Ok(())
     }
 
-    /// Query messages from database
-    /// Placeholder implementation - would execute SQL SELECT
+    /// Queries the outbound messages within `range`, ordered by sequence
+    /// number.
     pub fn query_messages_from_db(&self, range: Range<u64>) -> Result<QueryResult, BackendError> {
         log::debug!("Database backend: querying messages for range {range:?}");
-        // Placeholder for SQL: SELECT seq_num, content FROM messages WHERE seq_num BETWEEN ? AND ? ORDER BY seq_num
-        Ok(SmallVec::new())
+        let connection = self.connection()?;
+        let mut statement = connection.prepare_cached(
+            "SELECT seq_num, content FROM messages
+             WHERE session_id = ?1 AND direction = ?2 AND seq_num >= ?3 AND seq_num < ?4
+             ORDER BY seq_num",
+        )?;
+        let rows = statement.query_map(
+            rusqlite::params![
+                self.session_id.as_str(),
+                Direction::Outbound.as_str(),
+                to_sql_seq_num(range.start)?,
+                to_sql_seq_num(range.end)?
+            ],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)),
+        )?;
+        let mut messages = QueryResult::new();
+        for row in rows {
+            let (seq_num, content) = row?;
+            messages.push((seq_num as u64, SmallVec::from_vec(content)));
+        }
+        Ok(messages)
+    }
+
+    /// Returns the persisted next expected inbound and outbound sequence
+    /// numbers of this session.
+    pub fn seq_numbers(&self) -> Result<SeqNumbers, BackendError> {
+        let connection = self.connection()?;
+        let (next_inbound, next_outbound) = connection.query_row(
+            "SELECT next_inbound, next_outbound FROM sessions WHERE session_id = ?1",
+            rusqlite::params![self.session_id.as_str()],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+        Ok(SeqNumbers {
+            next_inbound: next_inbound as u64,
+            next_outbound: next_outbound as u64,
+        })
+    }
+
+    /// Overwrites the persisted next expected sequence numbers of this
+    /// session.
+    pub fn set_seq_numbers(&self, seq_numbers: SeqNumbers) -> Result<(), BackendError> {
+        self.connection()?.execute(
+            "UPDATE sessions SET next_inbound = ?2, next_outbound = ?3 WHERE session_id = ?1",
+            rusqlite::params![
+                self.session_id.as_str(),
+                to_sql_seq_num(seq_numbers.next_inbound)?,
+                to_sql_seq_num(seq_numbers.next_outbound)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the creation time of this session as a FIX `UTCTimestamp`.
+    pub fn creation_time(&self) -> Result<String, BackendError> {
+        Ok(self.connection()?.query_row(
+            "SELECT creation_time FROM sessions WHERE session_id = ?1",
+            rusqlite::params![self.session_id.as_str()],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Deletes all stored messages of this session, resets both sequence
+    /// numbers to 1 and refreshes the creation time.
+    pub fn reset(&mut self) -> Result<(), BackendError> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction()?;
+        tx.execute(
+            "DELETE FROM messages WHERE session_id = ?1",
+            rusqlite::params![self.session_id.as_str()],
+        )?;
+        tx.execute(
+            "UPDATE sessions SET creation_time = ?2, next_inbound = 1, next_outbound = 1
+             WHERE session_id = ?1",
+            rusqlite::params![self.session_id.as_str(), utc_timestamp()],
+        )?;
+        tx.commit()?;
+        drop(connection);
+        self.memory_cache.on_sequence_reset()
     }
 }
 
+#[cfg(feature = "utils-rusqlite")]
 type QueryResult = SmallVec<[(u64, SmallVec<[u8; 1024]>); 16]>;
 
+#[cfg(feature = "utils-rusqlite")]
+fn to_sql_seq_num(seq_num: u64) -> Result<i64, BackendError> {
+    i64::try_from(seq_num).map_err(|_| BackendError::InvalidRange {
+        start: seq_num,
+        end: seq_num,
+    })
+}
+
+#[cfg(feature = "utils-rusqlite")]
+fn utc_timestamp() -> String {
+    chrono::Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string()
+}
+
+#[cfg(feature = "utils-rusqlite")]
 impl Backend for DatabaseBackend {
     type Error = BackendError;
 
@@ -550,45 +1129,57 @@ impl Backend for DatabaseBackend {
         &mut self,
         message: crate::tagvalue::Message<&[u8]>,
     ) -> Result<(), Self::Error> {
-        // Extract sequence number and message bytes before moving the message
-        let seq_num_result = message.get::<u64>(34); // MSG_SEQ_NUM = 34
-        let message_bytes = message.as_bytes().to_vec();
-
-        // Store in memory cache
-        let result = self.memory_cache.on_inbound_app_message(message);
-
-        // Store in database if sequence number is available
-        if let Ok(seq_num) = seq_num_result {
-            self.store_message_in_db(seq_num, &message_bytes, "inbound")?;
+        if let Ok(seq_num) = message.get::<u64>(34) {
+            self.store_message_in_db(seq_num, message.as_bytes(), Direction::Inbound)?;
         }
+        self.memory_cache.on_inbound_app_message(message)
+    }
 
-        result
+    fn on_inbound_message(
+        &mut self,
+        message: crate::tagvalue::Message<&[u8]>,
+        is_app: bool,
+    ) -> Result<(), Self::Error> {
+        if is_app {
+            return self.on_inbound_app_message(message);
+        }
+        // Session-level messages also advance the inbound sequence number.
+        if let Ok(seq_num) = message.get::<u64>(34) {
+            self.store_message_in_db(seq_num, message.as_bytes(), Direction::Inbound)?;
+        }
+        Ok(())
     }
 
     fn on_outbound_message(&mut self, message: &[u8]) -> Result<(), Self::Error> {
-        let result = self.memory_cache.on_outbound_message(message);
-
-        // Parse sequence number and store in database
-        // This is simplified - would need proper FIX message parsing
-        self.store_message_in_db(0, message, "outbound")?; // seq_num=0 as placeholder
-
-        result
+        let Some(seq_num) = msg_seq_num(message) else {
+            log::warn!("Database backend: outbound message without MsgSeqNum(34), not stored");
+            return Ok(());
+        };
+        self.store_message_in_db(seq_num, message, Direction::Outbound)?;
+        self.memory_cache.store_outbound_message(seq_num, message)
     }
 
-    fn on_resend_request(&mut self, range: Range<u64>) -> Result<(), Self::Error> {
+    fn on_resend_request(&mut self, range: Range<u64>) -> Result<Vec<Vec<u8>>, Self::Error> {
         log::info!("Database backend: processing resend request for range {range:?}");
 
-        // Query from database for more comprehensive coverage
-        let db_messages = self.query_messages_from_db(range.clone())?;
-
-        // Fallback to memory cache
-        let cache_result = self.memory_cache.on_resend_request(range);
+        if range.start > range.end {
+            return Err(BackendError::InvalidRange {
+                start: range.start,
+                end: range.end,
+            });
+        }
 
+        // The database is the source of truth: it also covers messages sent
+        // before the last restart.
+        let db_messages = self.query_messages_from_db(range)?;
         log::info!(
             "Found {} messages in database for resend",
             db_messages.len()
         );
-        cache_result
+        Ok(db_messages
+            .into_iter()
+            .map(|(_seq_num, message)| message.to_vec())
+            .collect())
     }
 
     fn on_successful_handshake(&mut self) -> Result<(), Self::Error> {
@@ -603,6 +1194,34 @@ impl Backend for DatabaseBackend {
     fn pending_message(&mut self) -> Option<&[u8]> {
         self.memory_cache.pending_message()
     }
+
+    fn stored_seq_numbers(&self) -> Option<SeqNumbers> {
+        self.seq_numbers().ok()
+    }
+
+    fn on_seq_numbers_changed(&mut self, seq_numbers: SeqNumbers) -> Result<(), Self::Error> {
+        self.set_seq_numbers(seq_numbers)
+    }
+
+    fn creation_time(&self) -> Option<SystemTime> {
+        let creation_time = DatabaseBackend::creation_time(self).ok()?;
+        crate::field_types::Timestamp::deserialize(creation_time.as_bytes())
+            .ok()?
+            .to_system_time()
+    }
+
+    fn on_sequence_reset(&mut self) -> Result<(), Self::Error> {
+        self.reset()
+    }
+}
+
+/// Extracts the `MsgSeqNum <34>` value from a raw, SOH-separated FIX message.
+pub(crate) fn msg_seq_num(message: &[u8]) -> Option<u64> {
+    message
+        .split(|byte| *byte == b'\x01')
+        .find_map(|field| field.strip_prefix(b"34="))
+        .and_then(|value| std::str::from_utf8(value).ok())
+        .and_then(|value| value.parse().ok())
 }
 
 /// A trait for FIX applications (legacy compatibility)
@@ -614,6 +1233,7 @@ pub trait Application {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::UNIX_EPOCH;
 
     #[test]
     fn test_memory_backend_creation() {
@@ -649,6 +1269,118 @@ mod tests {
         assert_eq!(messages.len(), 2);
     }
 
+    #[test]
+    fn test_memory_backend_retention_limits() {
+        let retention = MemoryRetention {
+            max_messages: 3,
+            ..MemoryRetention::default()
+        };
+        let mut backend = MemoryBackend::with_retention("SENDER", "TARGET", retention).unwrap();
+        for seq_num in 1..=5 {
+            backend
+                .store_outbound_message(seq_num, b"message1")
+                .unwrap();
+        }
+        assert_eq!(backend.stored(Direction::Outbound), (3, 24));
+        assert!(backend.get_messages_for_resend(1..3).is_empty());
+
+        let retention = MemoryRetention {
+            max_bytes: Some(20),
+            max_age: Some(Duration::from_secs(60)),
+            ..MemoryRetention::default()
+        };
+        let mut backend = MemoryBackend::with_retention("SENDER", "TARGET", retention).unwrap();
+        let clock = crate::session::MockClock::default();
+        backend.set_clock(Arc::new(clock.clone()));
+        for seq_num in 1..=3 {
+            backend.store_inbound_message(seq_num, b"message1").unwrap();
+        }
+        assert_eq!(backend.stored(Direction::Inbound), (2, 16));
+        assert!(!backend.is_duplicate(1));
+
+        clock.advance(Duration::from_secs(61));
+        backend.store_inbound_message(4, b"message1").unwrap();
+        assert_eq!(backend.stored(Direction::Inbound), (1, 8));
+        assert!(backend.is_duplicate(4));
+    }
+
+    #[test]
+    fn test_memory_backend_spills_evicted_messages() {
+        let spill_path = std::env::temp_dir().join("test_memory_backend_spill.journal");
+        let retention = MemoryRetention {
+            max_messages: 2,
+            spill_path: Some(spill_path),
+            ..MemoryRetention::default()
+        };
+        let mut backend = MemoryBackend::with_retention("SENDER", "TARGET", retention).unwrap();
+        for seq_num in 1..=4 {
+            let message = format!("35=D\x0134={seq_num}\x01");
+            backend.on_outbound_message(message.as_bytes()).unwrap();
+        }
+        assert_eq!(backend.stored(Direction::Outbound).0, 2);
+        assert_eq!(backend.spilled_messages(1..5).unwrap().len(), 2);
+
+        backend.queue_message(b"35=D\x01");
+        let resent: Vec<u64> = backend
+            .on_resend_request(1..5)
+            .unwrap()
+            .iter()
+            .filter_map(|message| msg_seq_num(message))
+            .collect();
+        assert_eq!(resent, [1, 2, 3, 4]);
+        assert_eq!(backend.pending_message(), Some(&b"35=D\x01"[..]));
+
+        backend.on_sequence_reset().unwrap();
+        assert!(backend.spilled_messages(0..u64::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_memory_backend_clones_share_the_spill() {
+        let spill_path = std::env::temp_dir().join("test_memory_backend_clone_spill.journal");
+        let retention = MemoryRetention {
+            max_messages: 1,
+            spill_path: Some(spill_path),
+            ..MemoryRetention::default()
+        };
+        let mut backend = MemoryBackend::with_retention("SENDER", "TARGET", retention).unwrap();
+        for seq_num in 1..=2 {
+            let message = format!("35=D\x0134={seq_num}\x01");
+            backend.on_outbound_message(message.as_bytes()).unwrap();
+        }
+        let mut clone = backend.clone();
+        assert_eq!(clone.spilled_messages(0..u64::MAX).unwrap().len(), 1);
+
+        clone.on_outbound_message(b"35=D\x0134=3\x01").unwrap();
+        assert_eq!(backend.spilled_messages(0..u64::MAX).unwrap().len(), 2);
+        clone.on_sequence_reset().unwrap();
+        assert!(backend.spilled_messages(0..u64::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_memory_backend_compacts_the_spill() {
+        let spill_path = std::env::temp_dir().join("test_memory_backend_spill_compaction.journal");
+        let retention = MemoryRetention {
+            max_messages: 1,
+            spill_path: Some(spill_path),
+            max_spilled_messages: Some(2),
+            ..MemoryRetention::default()
+        };
+        let mut backend = MemoryBackend::with_retention("SENDER", "TARGET", retention).unwrap();
+        for seq_num in 1..=6 {
+            let message = format!("35=D\x0134={seq_num}\x01");
+            backend.on_outbound_message(message.as_bytes()).unwrap();
+        }
+        // Once 1 to 4 were spilled, they were compacted down to the newest
+        // two, and 5 was spilled after that.
+        let spilled: Vec<_> = backend
+            .spilled_messages(0..u64::MAX)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.seq_num)
+            .collect();
+        assert_eq!(spilled, [3, 4, 5]);
+    }
+
     #[test]
     fn test_file_backend_creation() {
         let temp_path = std::env::temp_dir().join("test_fix_messages.txt");
@@ -657,9 +1389,152 @@ mod tests {
         assert_eq!(backend.target_comp_id(), b"TARGET");
 
         // Cleanup
+        let _ = std::fs::remove_file(&temp_path);
+        let _ = std::fs::remove_file(temp_path.with_extension("txt.seqnums"));
+    }
+
+    #[test]
+    fn test_file_backend_recovers_after_restart() {
+        let temp_path = std::env::temp_dir().join("test_fix_messages_recovery.journal");
+        let seq_num_path = temp_path.with_extension("journal.seqnums");
+        let _ = std::fs::remove_file(&temp_path);
+        let _ = std::fs::remove_file(&seq_num_path);
+        {
+            let mut backend = FileBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+            for seq_num in 1..=3 {
+                let message = format!("8=FIX.4.4\x019=20\x0135=D\x0134={seq_num}\x0110=000\x01");
+                backend.on_outbound_message(message.as_bytes()).unwrap();
+            }
+            backend
+                .store_message(5, b"8=FIX.4.4\x0134=5\x01", Direction::Inbound)
+                .unwrap();
+            // No explicit save: the journal must already be on disk.
+        }
+        assert_eq!(
+            std::fs::read_to_string(&seq_num_path).unwrap(),
+            "0000000004 : 0000000006"
+        );
+
+        let mut backend = FileBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+        let seq_numbers = backend.seq_numbers().unwrap();
+        assert_eq!(seq_numbers.next_outbound(), 4);
+        assert_eq!(seq_numbers.next_inbound(), 6);
+        assert!(backend.memory_cache.is_duplicate(5));
+
+        let resent = backend.on_resend_request(2..4).unwrap();
+        assert_eq!(resent.len(), 2);
+        assert_eq!(msg_seq_num(&resent[0]), Some(2));
+
+        let _ = std::fs::remove_file(temp_path);
+        let _ = std::fs::remove_file(seq_num_path);
+    }
+
+    #[test]
+    fn test_file_backend_persists_creation_time() {
+        let temp_path = std::env::temp_dir().join("test_fix_messages_creation_time.journal");
+        let seq_num_path = temp_path.with_extension("journal.seqnums");
+        let session_path = temp_path.with_extension("journal.session");
+        let _ = std::fs::remove_file(&temp_path);
+        let _ = std::fs::remove_file(&seq_num_path);
+        let _ = std::fs::remove_file(&session_path);
+
+        let creation_time = FileBackend::new("SENDER", "TARGET", &temp_path)
+            .unwrap()
+            .creation_time()
+            .unwrap();
+        assert_eq!(std::fs::read(&session_path).unwrap().len(), 17);
+        let backend = FileBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+        assert_eq!(backend.creation_time().unwrap(), creation_time);
+
+        std::fs::write(&session_path, "20240102-09:00:00").unwrap();
+        let mut backend = FileBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+        assert_eq!(
+            Backend::creation_time(&backend),
+            Some(UNIX_EPOCH + Duration::from_secs(1_704_186_000))
+        );
+        backend.reset().unwrap();
+        assert!(backend.creation_time().unwrap() >= creation_time);
+
         let _ = std::fs::remove_file(temp_path);
+        let _ = std::fs::remove_file(seq_num_path);
+        let _ = std::fs::remove_file(session_path);
     }
 
+    #[test]
+    fn test_file_backend_trusts_journal_over_stale_seq_num_file() {
+        let temp_path = std::env::temp_dir().join("test_fix_messages_stale.journal");
+        let seq_num_path = temp_path.with_extension("journal.seqnums");
+        let _ = std::fs::remove_file(&temp_path);
+        let _ = std::fs::remove_file(&seq_num_path);
+        {
+            let mut backend = FileBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+            backend
+                .on_outbound_message(b"8=FIX.4.4\x0134=1\x01")
+                .unwrap();
+            backend
+                .on_outbound_message(b"8=FIX.4.4\x0134=2\x01")
+                .unwrap();
+        }
+        // Simulates a crash between the journal append and the sequence
+        // number update.
+        std::fs::write(&seq_num_path, "0000000002 : 0000000001").unwrap();
+
+        let backend = FileBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+        assert_eq!(backend.seq_numbers().unwrap().next_outbound(), 3);
+
+        let _ = std::fs::remove_file(temp_path);
+        let _ = std::fs::remove_file(seq_num_path);
+    }
+
+    #[test]
+    fn test_file_backend_compaction_and_reset() {
+        let temp_path = std::env::temp_dir().join("test_fix_messages_compaction.journal");
+        let seq_num_path = temp_path.with_extension("journal.seqnums");
+        let _ = std::fs::remove_file(&temp_path);
+        let _ = std::fs::remove_file(&seq_num_path);
+        let config = FileBackendConfig {
+            fsync_policy: FsyncPolicy::Never,
+            compaction_threshold: Some(4),
+            retained_messages: 2,
+        };
+        let mut backend = FileBackend::with_config("SENDER", "TARGET", &temp_path, config).unwrap();
+        for seq_num in 1..=5 {
+            let message = format!("8=FIX.4.4\x0134={seq_num}\x01");
+            backend.on_outbound_message(message.as_bytes()).unwrap();
+        }
+        let records = backend.store().unwrap().journal.records().unwrap();
+        assert_eq!(
+            records.iter().map(|r| r.seq_num).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert_eq!(backend.seq_numbers().unwrap().next_outbound(), 6);
+
+        backend
+            .set_seq_numbers(SeqNumbers {
+                next_inbound: 1,
+                next_outbound: 5,
+            })
+            .unwrap();
+        let records = backend.store().unwrap().journal.records().unwrap();
+        assert_eq!(records.len(), 2);
+
+        backend.reset().unwrap();
+        assert_eq!(backend.seq_numbers().unwrap().next_outbound(), 1);
+        assert!(
+            backend
+                .store()
+                .unwrap()
+                .journal
+                .records()
+                .unwrap()
+                .is_empty()
+        );
+
+        let _ = std::fs::remove_file(temp_path);
+        let _ = std::fs::remove_file(seq_num_path);
+    }
+
+    #[cfg(feature = "utils-rusqlite")]
     #[test]
     fn test_database_backend_creation() {
         let temp_path = std::env::temp_dir().join("test_fix_messages.db");
@@ -670,4 +1545,54 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(temp_path);
     }
+
+    #[cfg(feature = "utils-rusqlite")]
+    #[test]
+    fn test_database_backend_replays_from_disk_after_restart() {
+        let temp_path = std::env::temp_dir().join("test_fix_messages_replay.db");
+        let _ = std::fs::remove_file(&temp_path);
+        {
+            let mut backend = DatabaseBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+            for seq_num in 1..=3 {
+                let message = format!("8=FIX.4.4\x019=20\x0135=D\x0134={seq_num}\x0110=000\x01");
+                backend.on_outbound_message(message.as_bytes()).unwrap();
+            }
+        }
+
+        // A fresh instance only has the database to go by.
+        let mut backend = DatabaseBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+        let stored = backend.query_messages_from_db(2..4).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].0, 2);
+        assert_eq!(backend.seq_numbers().unwrap().next_outbound(), 4);
+
+        let resent = backend.on_resend_request(1..3).unwrap();
+        assert_eq!(resent.len(), 2);
+        assert_eq!(msg_seq_num(&resent[0]), Some(1));
+
+        let _ = std::fs::remove_file(temp_path);
+    }
+
+    #[cfg(feature = "utils-rusqlite")]
+    #[test]
+    fn test_database_backend_seq_numbers_and_reset() {
+        let temp_path = std::env::temp_dir().join("test_fix_messages_seqnums.db");
+        let _ = std::fs::remove_file(&temp_path);
+        let mut backend = DatabaseBackend::new("SENDER", "TARGET", &temp_path).unwrap();
+        assert_eq!(backend.session_id(), "SENDER->TARGET");
+        assert_eq!(backend.seq_numbers().unwrap().next_inbound(), 1);
+
+        backend
+            .store_message_in_db(7, b"inbound", Direction::Inbound)
+            .unwrap();
+        assert_eq!(backend.seq_numbers().unwrap().next_inbound(), 8);
+        assert_eq!(backend.seq_numbers().unwrap().next_outbound(), 1);
+
+        backend.reset().unwrap();
+        assert_eq!(backend.seq_numbers().unwrap().next_inbound(), 1);
+        assert!(backend.creation_time().unwrap().len() >= 17);
+        assert!(Backend::creation_time(&backend).is_some());
+
+        let _ = std::fs::remove_file(temp_path);
+    }
 }
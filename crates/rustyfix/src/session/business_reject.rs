@@ -0,0 +1,131 @@
+use super::backends::msg_seq_num;
+use crate::tags::raw_msg_type;
+
+/// The `BusinessRejectReason <380>` of a `BusinessMessageReject <j>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BusinessRejectReason {
+    /// Other.
+    Other,
+    /// Unknown ID.
+    UnknownId,
+    /// Unknown Security.
+    UnknownSecurity,
+    /// Unsupported Message Type.
+    UnsupportedMessageType,
+    /// Application not available.
+    ApplicationNotAvailable,
+    /// Conditionally required field missing.
+    ConditionallyRequiredFieldMissing,
+    /// Not authorized.
+    NotAuthorized,
+    /// DeliverTo firm not available at this time.
+    DeliverToFirmNotAvailable,
+    /// Invalid price increment.
+    InvalidPriceIncrement,
+}
+
+impl BusinessRejectReason {
+    /// Returns the value of `BusinessRejectReason <380>`.
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Other => 0,
+            Self::UnknownId => 1,
+            Self::UnknownSecurity => 2,
+            Self::UnsupportedMessageType => 3,
+            Self::ApplicationNotAvailable => 4,
+            Self::ConditionallyRequiredFieldMissing => 5,
+            Self::NotAuthorized => 6,
+            Self::DeliverToFirmNotAvailable => 7,
+            Self::InvalidPriceIncrement => 18,
+        }
+    }
+}
+
+/// The contents of a `BusinessMessageReject <j>`, i.e. the rejection of an
+/// application message that is valid at the session level.
+///
+/// Build it with
+/// [`FixConnection::business_reject`](super::FixConnection::business_reject) or
+/// send it through
+/// [`SessionHandle::business_reject`](super::SessionHandle::business_reject).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusinessReject {
+    /// The `RefSeqNum <45>`, i.e. the `MsgSeqNum <34>` of the rejected
+    /// message.
+    pub ref_seq_num: u64,
+    /// The `RefMsgType <372>`, i.e. the `MsgType <35>` of the rejected
+    /// message.
+    pub ref_msg_type: String,
+    /// The `BusinessRejectReason <380>`.
+    pub reason: BusinessRejectReason,
+    /// The `BusinessRejectRefID <379>`, e.g. the `ClOrdID <11>` of a
+    /// rejected order, if any.
+    pub ref_id: Option<String>,
+    /// The `Text <58>`, if any.
+    pub text: Option<String>,
+}
+
+impl BusinessReject {
+    /// Creates a new [`BusinessReject`] without `BusinessRejectRefID <379>`
+    /// and `Text <58>`.
+    pub fn new(
+        ref_seq_num: u64,
+        ref_msg_type: impl Into<String>,
+        reason: BusinessRejectReason,
+    ) -> Self {
+        Self {
+            ref_seq_num,
+            ref_msg_type: ref_msg_type.into(),
+            reason,
+            ref_id: None,
+            text: None,
+        }
+    }
+
+    /// Creates a [`BusinessReject`] of the raw inbound `message`, e.g. as
+    /// received from [`SessionHandle::recv`](super::SessionHandle::recv).
+    /// Returns [`None`] if `message` has no `MsgSeqNum <34>` or
+    /// `MsgType <35>`.
+    pub fn of(message: &[u8], reason: BusinessRejectReason) -> Option<Self> {
+        let ref_msg_type = raw_msg_type(message)?;
+        Some(Self::new(
+            msg_seq_num(message)?,
+            String::from_utf8_lossy(ref_msg_type),
+            reason,
+        ))
+    }
+
+    /// Sets the `Text <58>`.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Sets the `BusinessRejectRefID <379>`.
+    pub fn with_ref_id(mut self, ref_id: impl Into<String>) -> Self {
+        self.ref_id = Some(ref_id.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn of_raw_message() {
+        let message =
+            b"8=FIX.4.4\x019=40\x0135=D\x0134=7\x0149=A\x0156=B\x0111=ORD-1\x0110=000\x01";
+        let reject = BusinessReject::of(message, BusinessRejectReason::UnknownSecurity)
+            .unwrap()
+            .with_ref_id("ORD-1");
+        assert_eq!(reject.ref_seq_num, 7);
+        assert_eq!(reject.ref_msg_type, "D");
+        assert_eq!(reject.reason.code(), 2);
+        assert_eq!(reject.ref_id.as_deref(), Some("ORD-1"));
+        assert_eq!(
+            BusinessReject::of(b"8=FIX.4.4\x0135=D\x01", BusinessRejectReason::Other),
+            None
+        );
+    }
+}
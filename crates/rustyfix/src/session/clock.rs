@@ -0,0 +1,178 @@
+use crate::field_types::Timestamp;
+use futures_timer::Delay;
+use quanta::Instant;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Poll, Waker};
+use std::time::{Duration, SystemTime};
+
+/// The source of time of a FIX session: monotonic time for timeouts and
+/// intervals, and wall clock time for `SendingTime <52>` and schedules.
+///
+/// [`FixConnection`](super::FixConnection), [`LlEventLoop`](super::LlEventLoop)
+/// and [`MessageBuilder`](super::MessageBuilder) use the [`SystemClock`]
+/// unless told otherwise. Tests can install a [`MockClock`] instead.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Returns the current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall clock time.
+    fn system_time(&self) -> SystemTime;
+
+    /// Returns the current wall clock time as a UTC [`Timestamp`] with
+    /// millisecond precision.
+    ///
+    /// # Panics
+    /// Panics if the wall clock is before the UNIX epoch.
+    fn timestamp(&self) -> Timestamp {
+        Timestamp::from_system_time(self.system_time())
+            .expect("the wall clock is before the UNIX epoch")
+    }
+
+    /// Returns a future that completes once `duration` has passed according
+    /// to this clock. A [`Delay`] by default.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(Delay::new(duration))
+    }
+}
+
+/// The [`Clock`] of the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A [`Clock`] that only moves when told to, for deterministic tests of
+/// timeouts, latency checks and schedules.
+///
+/// Clones share the same time, so a test can keep one and advance the clock
+/// installed on a session. Timers started with [`Clock::sleep`], e.g. the
+/// ones of [`LlEventLoop::next_event`](super::LlEventLoop::next_event), fire
+/// as soon as the clock is advanced past them, and never on their own.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    origin: Instant,
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    elapsed: Duration,
+    system_time: SystemTime,
+    /// Of pending [`Clock::sleep`] futures, woken on every advance.
+    sleepers: Vec<Waker>,
+}
+
+impl MockClock {
+    /// Creates a [`MockClock`] whose wall clock reads `system_time`.
+    pub fn new(system_time: SystemTime) -> Self {
+        Self {
+            origin: Instant::now(),
+            state: Arc::new(Mutex::new(MockState {
+                elapsed: Duration::ZERO,
+                system_time,
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Moves both monotonic and wall clock time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.lock();
+        state.elapsed += duration;
+        state.system_time += duration;
+        let sleepers = std::mem::take(&mut state.sleepers);
+        drop(state);
+        sleepers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Sets the wall clock to `system_time`, e.g. to simulate a time
+    /// adjustment. Monotonic time doesn't change.
+    pub fn set_system_time(&self, system_time: SystemTime) {
+        self.lock().system_time = system_time;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for MockClock {
+    /// Creates a [`MockClock`] whose wall clock starts at the current time.
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.origin + self.lock().elapsed
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.lock().system_time
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let clock = self.clone();
+        let deadline = self.lock().elapsed + duration;
+        Box::pin(futures::future::poll_fn(move |cx| {
+            let mut state = clock.lock();
+            if state.elapsed >= deadline {
+                return Poll::Ready(());
+            }
+            if !state.sleepers.iter().any(|w| w.will_wake(cx.waker())) {
+                state.sleepers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_704_204_000));
+        let copy = clock.clone();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+        copy.advance(Duration::from_millis(1500));
+        assert_eq!(clock.now() - start, Duration::from_millis(1500));
+        assert_eq!(
+            clock.timestamp(),
+            Timestamp::parse(b"20240102-14:00:01.500").unwrap()
+        );
+
+        clock.set_system_time(UNIX_EPOCH);
+        assert_eq!(clock.system_time(), UNIX_EPOCH);
+        assert_eq!(clock.now() - start, Duration::from_millis(1500));
+    }
+
+    #[tokio::test]
+    async fn mock_sleep_ends_when_the_clock_is_advanced() {
+        let clock = MockClock::default();
+        let mut sleep = clock.sleep(Duration::from_secs(30));
+        assert!(futures::poll!(&mut sleep).is_pending());
+        clock.advance(Duration::from_secs(29));
+        assert!(futures::poll!(&mut sleep).is_pending());
+        let advance = async {
+            tokio::task::yield_now().await;
+            clock.advance(Duration::from_secs(1));
+        };
+        tokio::join!(sleep, advance);
+    }
+}
@@ -1,7 +1,15 @@
 use super::{Environment, MsgSeqNumCounter, SeqNumbers};
+use crate::FieldType;
+use crate::field_types::{Time, Tz};
 use std::marker::PhantomData;
 use std::num::NonZeroU64;
-use std::time::Duration;
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MILLIS_PER_DAY: i64 = 24 * 3600 * 1000;
+const MILLIS_PER_WEEK: i64 = 7 * MILLIS_PER_DAY;
+// 1970-01-01 was a Thursday, so weeks start three days before the epoch.
+const FIRST_MONDAY: i64 = -3 * MILLIS_PER_DAY;
 
 /// Collection of configuration options related to
 /// [`FixConnection`](super::FixConnection).
@@ -24,11 +32,72 @@ pub trait Configure: Clone + Default {
         Duration::from_secs(3)
     }
 
+    /// How far the counterparty's clock may be off from ours, on top of
+    /// [`Configure::max_allowed_latency`] for messages from the past, and on
+    /// its own for messages from the future. Zero by default.
+    fn max_clock_skew(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Rejects inbound messages whose `SendingTime <52>` is further than
+    /// [`Configure::max_allowed_latency`] from the current time, and logs
+    /// out. QuickFIX's `CheckLatency`. `true` by default.
+    fn check_latency(&self) -> bool {
+        true
+    }
+
+    /// Rejects inbound messages whose `SenderCompID <49>` and
+    /// `TargetCompID <56>` don't match the session, and logs out. QuickFIX's
+    /// `CheckCompID`. `true` by default.
+    fn check_comp_id(&self) -> bool {
+        true
+    }
+
+    /// Validates inbound messages against the dictionary of the session, see
+    /// [`SessionValidator`](crate::validation::SessionValidator). QuickFIX's
+    /// `UseDataDictionary`. `true` by default.
+    fn use_data_dictionary(&self) -> bool {
+        true
+    }
+
+    /// Rejects inbound messages with header fields after the body or body
+    /// fields after the trailer. QuickFIX's `ValidateFieldsOutOfOrder`. `true`
+    /// by default.
+    fn validate_fields_out_of_order(&self) -> bool {
+        true
+    }
+
+    /// Rejects inbound messages with empty fields. QuickFIX's
+    /// `ValidateFieldsHaveValues`. `true` by default.
+    fn validate_fields_have_values(&self) -> bool {
+        true
+    }
+
+    /// Rejects inbound messages with user-defined fields (tags 5000 and above)
+    /// that the dictionary doesn't define for their message type. QuickFIX's
+    /// `ValidateUserDefinedFields`. `true` by default.
+    fn validate_user_defined_fields(&self) -> bool {
+        true
+    }
+
+    /// Accepts inbound messages with fields (below tag 5000) that the
+    /// dictionary doesn't define for their message type. QuickFIX's
+    /// `AllowUnknownMsgFields`. `false` by default.
+    fn allow_unknown_msg_fields(&self) -> bool {
+        false
+    }
+
     /// The FIX BeginString, e.g. `FIX.4.4`.
     fn begin_string(&self) -> &[u8] {
         b"FIX.4.4"
     }
 
+    /// The `DefaultApplVerID <1137>` sent with the `Logon <A>` of FIXT.1.1
+    /// sessions, e.g. `9` for FIX 5.0 SP2. [`None`] by default.
+    fn default_appl_ver_id(&self) -> Option<&[u8]> {
+        None
+    }
+
     /// The CompID of the sender.
     fn sender_comp_id(&self) -> &[u8] {
         b"SENDER_COMP"
@@ -48,6 +117,281 @@ pub trait Configure: Clone + Default {
     fn heartbeat(&self) -> Duration {
         Duration::from_secs(30)
     }
+
+    /// The maximum number of inbound messages that are queued while a
+    /// sequence gap is recovered. Messages beyond this limit are dropped and
+    /// recovered through the `ResendRequest <2>` instead. 10000 by default.
+    fn max_queued_messages(&self) -> usize {
+        10_000
+    }
+
+    /// The maximum number of messages requested by a single
+    /// `ResendRequest <2>`, for counterparties that cap it. Larger gaps are
+    /// recovered chunk by chunk. [`None`] by default, i.e. a single request
+    /// with `EndSeqNo <16>` = 0.
+    fn max_resend_range(&self) -> Option<u64> {
+        None
+    }
+
+    /// Starts both sequence numbers over from 1 on every `Logon <A>`:
+    /// initiators send theirs with `ResetSeqNumFlag <141>` = Y, acceptors
+    /// reset before processing the counterparty's. QuickFIX's `ResetOnLogon`.
+    /// `false` by default.
+    fn reset_on_logon(&self) -> bool {
+        false
+    }
+
+    /// Starts both sequence numbers over from 1 once a `Logout <5>` exchange
+    /// completes. QuickFIX's `ResetOnLogout`. `false` by default.
+    fn reset_on_logout(&self) -> bool {
+        false
+    }
+
+    /// Starts both sequence numbers over from 1 whenever the transport is
+    /// closed, for whatever reason. QuickFIX's `ResetOnDisconnect`. `false`
+    /// by default.
+    fn reset_on_disconnect(&self) -> bool {
+        false
+    }
+
+    /// The [`SessionSchedule`] of the session. [`SessionSchedule::NonStop`] by
+    /// default.
+    fn schedule(&self) -> SessionSchedule {
+        SessionSchedule::NonStop
+    }
+
+    /// Returns `true` if the session is allowed to be connected at `now`.
+    /// Initiators don't (re)connect outside of this window. Follows
+    /// [`Configure::schedule`] by default.
+    fn is_session_time(&self, now: SystemTime) -> bool {
+        self.schedule().is_session_time(now)
+    }
+}
+
+/// Day of the week, for weekly [`SessionSchedule`]s.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    fn millis_since_monday(self) -> i64 {
+        self as i64 * MILLIS_PER_DAY
+    }
+}
+
+/// When a FIX session is live, i.e. QuickFIX's `StartTime`, `EndTime`,
+/// `StartDay` and `EndDay` settings.
+///
+/// Windows include their start and exclude their end. A window whose end is
+/// earlier than its start wraps around midnight (or the end of the week); a
+/// window whose end equals its start lasts a full day (or week). Times are
+/// local to a [`SessionTz`], which follows daylight saving time changes if
+/// it's an IANA time zone.
+///
+/// Sequence numbers belong to a single window: the
+/// [`Session`](super::Session) driver logs out when its window ends and both
+/// sequence numbers start over from 1 in the next one.
+///
+/// # Examples
+///
+/// ```
+/// use rustyfix::field_types::Time;
+/// use rustyfix::session::{SessionSchedule, SessionTz};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// // 08:00 to 17:00 in New York.
+/// let schedule = SessionSchedule::Daily {
+///     start: Time::from_hmsm(8, 0, 0, 0).unwrap(),
+///     end: Time::from_hmsm(17, 0, 0, 0).unwrap(),
+///     tz: SessionTz::parse("America/New_York").unwrap(),
+/// };
+/// // 2024-01-02T14:00:00Z, i.e. 09:00 EST.
+/// let winter = UNIX_EPOCH + Duration::from_secs(1_704_204_000);
+/// assert!(schedule.is_session_time(winter));
+/// assert!(!schedule.is_session_time(winter + Duration::from_secs(8 * 3600)));
+/// // 2024-07-02T12:30:00Z, i.e. 08:30 EDT.
+/// let summer = UNIX_EPOCH + Duration::from_secs(1_719_923_400);
+/// assert!(schedule.is_session_time(summer));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SessionSchedule {
+    /// The session is always live and sequence numbers never reset.
+    #[default]
+    NonStop,
+    /// The session is live between `start` and `end` every day.
+    #[allow(missing_docs)]
+    Daily {
+        start: Time,
+        end: Time,
+        tz: SessionTz,
+    },
+    /// The session is live from `start` on `start_day` until `end` on
+    /// `end_day`, every week.
+    #[allow(missing_docs)]
+    Weekly {
+        start_day: Weekday,
+        start: Time,
+        end_day: Weekday,
+        end: Time,
+        tz: SessionTz,
+    },
+}
+
+impl SessionSchedule {
+    /// Returns `true` if `now` is within a window of `self`.
+    pub fn is_session_time(&self, now: SystemTime) -> bool {
+        *self == Self::NonStop || self.window(now).is_some()
+    }
+
+    /// Returns the start and end of the window that contains `now`, or
+    /// [`None`] if `now` is outside of the schedule. Always [`None`] for
+    /// [`SessionSchedule::NonStop`], which has no windows.
+    pub fn window(&self, now: SystemTime) -> Option<Range<SystemTime>> {
+        let (period, origin, start, end, tz) = match *self {
+            Self::NonStop => return None,
+            Self::Daily { start, end, tz } => (
+                MILLIS_PER_DAY,
+                0,
+                millis_of_day(start),
+                millis_of_day(end),
+                tz,
+            ),
+            Self::Weekly {
+                start_day,
+                start,
+                end_day,
+                end,
+                tz,
+            } => (
+                MILLIS_PER_WEEK,
+                FIRST_MONDAY,
+                start_day.millis_since_monday() + millis_of_day(start),
+                end_day.millis_since_monday() + millis_of_day(end),
+                tz,
+            ),
+        };
+        let utc = unix_millis(now);
+        let local = utc + tz.offset_millis(utc);
+        let length = match (end - start).rem_euclid(period) {
+            0 => period,
+            length => length,
+        };
+        let window_start = origin + start + (local - origin - start).div_euclid(period) * period;
+        // The UTC offset may change within the window.
+        let utc_start = tz.to_utc_millis(window_start);
+        let utc_end = tz.to_utc_millis(window_start + length);
+        if (utc_start..utc_end).contains(&utc) {
+            Some(from_unix_millis(utc_start)..from_unix_millis(utc_end))
+        } else {
+            None
+        }
+    }
+}
+
+/// The time zone of a [`SessionSchedule`], i.e. QuickFIX's `TimeZone`
+/// setting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SessionTz {
+    /// A fixed offset from UTC. Daylight saving time changes are not followed.
+    Fixed(Tz),
+    /// A time zone of the IANA database, e.g. `America/New_York`, including
+    /// its daylight saving time changes.
+    Named(chrono_tz::Tz),
+}
+
+impl SessionTz {
+    /// Coordinated Universal Time.
+    pub const UTC: Self = Self::Fixed(Tz::UTC);
+
+    /// Parses `UTC`, an IANA time zone name such as `Europe/London`, or a
+    /// fixed offset in the format of FIX `TZTimeOnly` fields, e.g. `-05`.
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("UTC") {
+            return Some(Self::UTC);
+        }
+        if let Ok(tz) = name.parse::<chrono_tz::Tz>() {
+            return Some(Self::Named(tz));
+        }
+        Tz::deserialize(name.as_bytes()).ok().map(Self::Fixed)
+    }
+
+    /// Returns the offset from UTC, in milliseconds, at `utc` milliseconds
+    /// since the UNIX epoch.
+    fn offset_millis(&self, utc: i64) -> i64 {
+        use chrono::{Offset, TimeZone};
+        match self {
+            Self::Fixed(tz) => i64::from(tz.offset_as_secs()) * 1000,
+            Self::Named(tz) => chrono::DateTime::from_timestamp_millis(utc)
+                .map(|utc| {
+                    let offset = tz.offset_from_utc_datetime(&utc.naive_utc());
+                    i64::from(offset.fix().local_minus_utc()) * 1000
+                })
+                .unwrap_or(0),
+        }
+    }
+
+    /// Converts `local` milliseconds since the local UNIX epoch to UTC. Local
+    /// times skipped by a daylight saving time change move forward by the
+    /// length of the gap; repeated ones resolve to their first occurrence.
+    fn to_utc_millis(self, local: i64) -> i64 {
+        use chrono::{LocalResult, Offset, TimeZone};
+        let Self::Named(tz) = self else {
+            return local - self.offset_millis(local);
+        };
+        let Some(naive) = chrono::DateTime::from_timestamp_millis(local).map(|t| t.naive_utc())
+        else {
+            return local;
+        };
+        match tz.offset_from_local_datetime(&naive) {
+            LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => {
+                local - i64::from(offset.fix().local_minus_utc()) * 1000
+            }
+            // The offset from before the gap.
+            LocalResult::None => local - self.offset_millis(local - MILLIS_PER_DAY),
+        }
+    }
+}
+
+impl From<Tz> for SessionTz {
+    fn from(tz: Tz) -> Self {
+        Self::Fixed(tz)
+    }
+}
+
+impl From<chrono_tz::Tz> for SessionTz {
+    fn from(tz: chrono_tz::Tz) -> Self {
+        Self::Named(tz)
+    }
+}
+
+fn millis_of_day(time: Time) -> i64 {
+    i64::from(time.hour()) * 3_600_000
+        + i64::from(time.minute()) * 60_000
+        + i64::from(time.second()) * 1000
+        + i64::from(time.milli())
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_millis() as i64,
+        Err(err) => -(err.duration().as_millis() as i64),
+    }
+}
+
+fn from_unix_millis(millis: i64) -> SystemTime {
+    if millis >= 0 {
+        UNIX_EPOCH + Duration::from_millis(millis as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_millis(millis.unsigned_abs())
+    }
 }
 
 /// The canonical implementor of [`Configure`]. Every setting can be changed.
@@ -59,7 +403,16 @@ pub struct Config {
 
     pub verify_test_indicator: bool,
     pub max_allowed_latency: Duration,
+    pub max_clock_skew: Duration,
+    pub check_latency: bool,
+    pub check_comp_id: bool,
+    pub use_data_dictionary: bool,
+    pub validate_fields_out_of_order: bool,
+    pub validate_fields_have_values: bool,
+    pub validate_user_defined_fields: bool,
+    pub allow_unknown_msg_fields: bool,
     pub begin_string: String,
+    pub default_appl_ver_id: Option<String>,
     pub environment: Environment,
     pub heartbeat: Duration,
     pub seq_numbers: SeqNumbers,
@@ -67,6 +420,12 @@ pub struct Config {
     pub msg_seq_num_outbound: MsgSeqNumCounter,
     pub sender_comp_id: String,
     pub target_comp_id: String,
+    pub schedule: SessionSchedule,
+    pub max_queued_messages: usize,
+    pub max_resend_range: Option<u64>,
+    pub reset_on_logon: bool,
+    pub reset_on_logout: bool,
+    pub reset_on_disconnect: bool,
 }
 
 impl Configure for Config {
@@ -78,6 +437,38 @@ impl Configure for Config {
         self.max_allowed_latency
     }
 
+    fn max_clock_skew(&self) -> Duration {
+        self.max_clock_skew
+    }
+
+    fn check_latency(&self) -> bool {
+        self.check_latency
+    }
+
+    fn check_comp_id(&self) -> bool {
+        self.check_comp_id
+    }
+
+    fn use_data_dictionary(&self) -> bool {
+        self.use_data_dictionary
+    }
+
+    fn validate_fields_out_of_order(&self) -> bool {
+        self.validate_fields_out_of_order
+    }
+
+    fn validate_fields_have_values(&self) -> bool {
+        self.validate_fields_have_values
+    }
+
+    fn validate_user_defined_fields(&self) -> bool {
+        self.validate_user_defined_fields
+    }
+
+    fn allow_unknown_msg_fields(&self) -> bool {
+        self.allow_unknown_msg_fields
+    }
+
     fn sender_comp_id(&self) -> &[u8] {
         self.sender_comp_id.as_bytes()
     }
@@ -90,6 +481,10 @@ impl Configure for Config {
         self.begin_string.as_bytes()
     }
 
+    fn default_appl_ver_id(&self) -> Option<&[u8]> {
+        self.default_appl_ver_id.as_deref().map(str::as_bytes)
+    }
+
     fn environment(&self) -> Environment {
         self.environment
     }
@@ -97,6 +492,30 @@ impl Configure for Config {
     fn heartbeat(&self) -> Duration {
         self.heartbeat
     }
+
+    fn schedule(&self) -> SessionSchedule {
+        self.schedule
+    }
+
+    fn max_queued_messages(&self) -> usize {
+        self.max_queued_messages
+    }
+
+    fn max_resend_range(&self) -> Option<u64> {
+        self.max_resend_range
+    }
+
+    fn reset_on_logon(&self) -> bool {
+        self.reset_on_logon
+    }
+
+    fn reset_on_logout(&self) -> bool {
+        self.reset_on_logout
+    }
+
+    fn reset_on_disconnect(&self) -> bool {
+        self.reset_on_disconnect
+    }
 }
 
 impl Default for Config {
@@ -105,7 +524,16 @@ impl Default for Config {
             phantom: PhantomData,
             verify_test_indicator: true,
             max_allowed_latency: Duration::from_secs(3),
+            max_clock_skew: Duration::ZERO,
+            check_latency: true,
+            check_comp_id: true,
+            use_data_dictionary: true,
+            validate_fields_out_of_order: true,
+            validate_fields_have_values: true,
+            validate_user_defined_fields: true,
+            allow_unknown_msg_fields: false,
             begin_string: "FIX.4.4".to_string(),
+            default_appl_ver_id: None,
             environment: Environment::Production { allow_test: true },
             heartbeat: Duration::from_secs(30),
             seq_numbers: SeqNumbers::new(NonZeroU64::new(1).unwrap(), NonZeroU64::new(1).unwrap()),
@@ -113,6 +541,12 @@ impl Default for Config {
             msg_seq_num_outbound: MsgSeqNumCounter::START,
             sender_comp_id: "SENDER_COMP".to_string(),
             target_comp_id: "TARGET_COMP".to_string(),
+            schedule: SessionSchedule::NonStop,
+            max_queued_messages: 10_000,
+            max_resend_range: None,
+            reset_on_logon: false,
+            reset_on_logout: false,
+            reset_on_disconnect: false,
         }
     }
 }
@@ -120,6 +554,7 @@ impl Default for Config {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::FieldType;
     use quickcheck_macros::quickcheck;
 
     #[derive(Default, Clone)]
@@ -138,6 +573,35 @@ mod test {
             config.verify_test_indicator(),
             ConfigDefault.verify_test_indicator()
         );
+        assert_eq!(config.max_clock_skew(), ConfigDefault.max_clock_skew());
+        assert_eq!(config.check_latency(), ConfigDefault.check_latency());
+        assert_eq!(config.check_comp_id(), ConfigDefault.check_comp_id());
+        assert_eq!(
+            config.use_data_dictionary(),
+            ConfigDefault.use_data_dictionary()
+        );
+        assert_eq!(
+            config.validate_fields_out_of_order(),
+            ConfigDefault.validate_fields_out_of_order()
+        );
+        assert_eq!(
+            config.validate_fields_have_values(),
+            ConfigDefault.validate_fields_have_values()
+        );
+        assert_eq!(
+            config.validate_user_defined_fields(),
+            ConfigDefault.validate_user_defined_fields()
+        );
+        assert_eq!(
+            config.allow_unknown_msg_fields(),
+            ConfigDefault.allow_unknown_msg_fields()
+        );
+        assert_eq!(config.reset_on_logon(), ConfigDefault.reset_on_logon());
+        assert_eq!(config.reset_on_logout(), ConfigDefault.reset_on_logout());
+        assert_eq!(
+            config.reset_on_disconnect(),
+            ConfigDefault.reset_on_disconnect()
+        );
     }
 
     #[quickcheck]
@@ -157,4 +621,168 @@ mod test {
         };
         config.verify_test_indicator() == verify
     }
+
+    /// 2024-01-01 was a Monday.
+    fn at(day: u64, hour: u64, minute: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_704_067_200 + day * 86_400 + hour * 3600 + minute * 60)
+    }
+
+    fn time(hour: u32, minute: u32) -> Time {
+        Time::from_hmsm(hour, minute, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn non_stop_is_always_live() {
+        let schedule = SessionSchedule::NonStop;
+        assert!(schedule.is_session_time(at(0, 0, 0)));
+        assert!(schedule.window(at(0, 0, 0)).is_none());
+        assert!(Config::default().is_session_time(UNIX_EPOCH));
+    }
+
+    #[test]
+    fn daily_window() {
+        let schedule = SessionSchedule::Daily {
+            start: time(8, 0),
+            end: time(17, 30),
+            tz: SessionTz::UTC,
+        };
+        assert!(!schedule.is_session_time(at(2, 7, 59)));
+        assert_eq!(
+            schedule.window(at(2, 8, 0)),
+            Some(at(2, 8, 0)..at(2, 17, 30))
+        );
+        assert!(schedule.is_session_time(at(2, 17, 29)));
+        assert!(!schedule.is_session_time(at(2, 17, 30)));
+    }
+
+    #[test]
+    fn daily_window_wraps_around_midnight() {
+        let schedule = SessionSchedule::Daily {
+            start: time(22, 0),
+            end: time(6, 0),
+            tz: SessionTz::UTC,
+        };
+        assert_eq!(
+            schedule.window(at(3, 2, 0)),
+            Some(at(2, 22, 0)..at(3, 6, 0))
+        );
+        assert_eq!(
+            schedule.window(at(3, 23, 0)),
+            Some(at(3, 22, 0)..at(4, 6, 0))
+        );
+        assert!(!schedule.is_session_time(at(3, 12, 0)));
+    }
+
+    #[test]
+    fn same_start_and_end_is_a_full_day() {
+        let schedule = SessionSchedule::Daily {
+            start: time(17, 0),
+            end: time(17, 0),
+            tz: SessionTz::UTC,
+        };
+        assert_eq!(
+            schedule.window(at(1, 16, 59)),
+            Some(at(0, 17, 0)..at(1, 17, 0))
+        );
+        assert_eq!(
+            schedule.window(at(1, 17, 0)),
+            Some(at(1, 17, 0)..at(2, 17, 0))
+        );
+    }
+
+    #[test]
+    fn daily_window_in_time_zone() {
+        let schedule = SessionSchedule::Daily {
+            start: time(9, 0),
+            end: time(17, 0),
+            tz: SessionTz::Fixed(Tz::deserialize(b"+02").unwrap()),
+        };
+        // 09:00 at UTC+2 is 07:00 UTC.
+        assert_eq!(
+            schedule.window(at(0, 7, 0)),
+            Some(at(0, 7, 0)..at(0, 15, 0))
+        );
+        assert!(!schedule.is_session_time(at(0, 15, 0)));
+    }
+
+    #[test]
+    fn daily_window_follows_daylight_saving_time() {
+        let schedule = SessionSchedule::Daily {
+            start: time(9, 0),
+            end: time(17, 0),
+            tz: SessionTz::parse("Europe/London").unwrap(),
+        };
+        // GMT in January, BST in July.
+        assert_eq!(
+            schedule.window(at(0, 9, 0)),
+            Some(at(0, 9, 0)..at(0, 17, 0))
+        );
+        let july = Duration::from_secs(181 * 86_400);
+        assert_eq!(
+            schedule.window(at(0, 8, 0) + july),
+            Some(at(0, 8, 0) + july..at(0, 16, 0) + july)
+        );
+        assert!(!schedule.is_session_time(at(0, 16, 0) + july));
+    }
+
+    #[test]
+    fn window_spanning_a_daylight_saving_time_change() {
+        let schedule = SessionSchedule::Daily {
+            start: time(22, 0),
+            end: time(6, 0),
+            tz: SessionTz::Named(chrono_tz::America::New_York),
+        };
+        // New York springs forward at 02:00 on 2024-03-10, so the window from
+        // 22:00 EST to 06:00 EDT only lasts 7 hours.
+        assert_eq!(
+            schedule.window(at(69, 5, 0)),
+            Some(at(69, 3, 0)..at(69, 10, 0))
+        );
+    }
+
+    #[test]
+    fn parses_time_zones() {
+        assert_eq!(SessionTz::parse("utc"), Some(SessionTz::UTC));
+        assert_eq!(
+            SessionTz::parse("America/New_York"),
+            Some(SessionTz::Named(chrono_tz::America::New_York))
+        );
+        assert_eq!(
+            SessionTz::parse("-05"),
+            Some(SessionTz::Fixed(Tz::deserialize(b"-05").unwrap()))
+        );
+        assert_eq!(SessionTz::parse("Mars/Olympus_Mons"), None);
+    }
+
+    #[test]
+    fn weekly_window() {
+        // Sunday 17:00 to Friday 17:00, like most FX venues.
+        let schedule = SessionSchedule::Weekly {
+            start_day: Weekday::Sunday,
+            start: time(17, 0),
+            end_day: Weekday::Friday,
+            end: time(17, 0),
+            tz: SessionTz::UTC,
+        };
+        let window = Some(at(6, 17, 0)..at(11, 17, 0));
+        assert_eq!(schedule.window(at(6, 17, 0)), window);
+        assert_eq!(schedule.window(at(9, 3, 0)), window);
+        assert!(!schedule.is_session_time(at(11, 17, 0)));
+        assert!(!schedule.is_session_time(at(12, 12, 0)));
+        assert!(!schedule.is_session_time(at(13, 16, 59)));
+    }
+
+    #[test]
+    fn config_schedule_drives_session_time() {
+        let config = Config {
+            schedule: SessionSchedule::Daily {
+                start: time(8, 0),
+                end: time(9, 0),
+                tz: SessionTz::UTC,
+            },
+            ..Default::default()
+        };
+        assert!(config.is_session_time(at(0, 8, 30)));
+        assert!(!config.is_session_time(at(0, 9, 30)));
+    }
 }
@@ -0,0 +1,158 @@
+use super::driver::Command;
+use super::{SeqNumbers, SessionError, SessionId, SessionState};
+use futures::channel::mpsc;
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// A point-in-time snapshot of a [`Session`](super::Session), as published by
+/// the session itself while it runs.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionStatus {
+    /// The state of the session layer.
+    pub state: SessionState,
+    /// The next expected inbound and outbound sequence numbers.
+    pub seq_numbers: SeqNumbers,
+    /// When the last message was received from the counterparty, if ever.
+    pub last_heartbeat: Option<SystemTime>,
+    /// Whether the session may log on. See [`SessionControl::disable`].
+    pub enabled: bool,
+}
+
+impl Default for SessionStatus {
+    fn default() -> Self {
+        Self {
+            state: SessionState::Disconnected,
+            seq_numbers: SeqNumbers::default(),
+            last_heartbeat: None,
+            enabled: true,
+        }
+    }
+}
+
+/// Operational controls of a [`Session`](super::Session), obtained through
+/// [`SessionHandle::control`](super::SessionHandle::control).
+///
+/// Unlike the [`SessionHandle`](super::SessionHandle), [`SessionControl`] is
+/// cheap to clone and doesn't receive application messages, so it can be
+/// handed over to an admin service. Commands sent while the session isn't
+/// running take effect once it runs again.
+#[derive(Debug, Clone)]
+pub struct SessionControl {
+    pub(super) commands: mpsc::UnboundedSender<Command>,
+    pub(super) status: Arc<Mutex<SessionStatus>>,
+}
+
+impl SessionControl {
+    /// Returns the latest [`SessionStatus`].
+    pub fn status(&self) -> SessionStatus {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Starts the Logout handshake with an optional `Text <58>`. Initiators
+    /// log back on according to their
+    /// [`ReconnectPolicy`](super::ReconnectPolicy); see
+    /// [`SessionControl::disable`] to keep the session down.
+    pub fn logout(&self, text: &str) -> Result<(), SessionError> {
+        self.command(Command::Logout {
+            text: text.to_string(),
+        })
+    }
+
+    /// Logs out and keeps the session from logging on again until
+    /// [`SessionControl::enable`] is called.
+    pub fn disable(&self, text: &str) -> Result<(), SessionError> {
+        self.set_enabled(false);
+        self.logout(text)
+    }
+
+    /// Allows a disabled session to log on again.
+    pub fn enable(&self) {
+        self.set_enabled(true);
+    }
+
+    /// Starts both sequence numbers over from 1. A logged on session logs out
+    /// first, and sequence numbers are reset once it's disconnected.
+    pub fn reset_seq_numbers(&self) -> Result<(), SessionError> {
+        self.command(Command::Reset)
+    }
+
+    /// Sends a `ResendRequest <2>` for `begin..=end`, regardless of any
+    /// detected sequence gap. `end` = 0 means everything from `begin` onwards.
+    pub fn resend_request(&self, begin: u64, end: u64) -> Result<(), SessionError> {
+        self.command(Command::ResendRequest { begin, end })
+    }
+
+    /// Sets the `MsgSeqNum <34>` of the next outbound message. See
+    /// [`FixConnection::set_next_sender_msg_seq_num`](super::FixConnection::set_next_sender_msg_seq_num).
+    pub fn set_next_sender_msg_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.command(Command::SetNextSenderMsgSeqNum(seq_num))
+    }
+
+    /// Sets the `MsgSeqNum <34>` expected from the next inbound message. See
+    /// [`FixConnection::set_next_target_msg_seq_num`](super::FixConnection::set_next_target_msg_seq_num).
+    pub fn set_next_target_msg_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.command(Command::SetNextTargetMsgSeqNum(seq_num))
+    }
+
+    pub(super) fn command(&self, command: Command) -> Result<(), SessionError> {
+        self.commands
+            .unbounded_send(command)
+            .map_err(|_| SessionError::Closed)
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.status
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .enabled = enabled;
+    }
+}
+
+/// A shared directory of [`SessionControl`]s by [`SessionId`], e.g. for an
+/// admin service to list and operate all sessions of the process. Clones
+/// share the same directory.
+///
+/// [`Acceptor`](super::Acceptor)s register every session they accept.
+#[derive(Debug, Clone, Default)]
+pub struct SessionControls {
+    sessions: Arc<Mutex<FxHashMap<SessionId, SessionControl>>>,
+}
+
+impl SessionControls {
+    /// Creates an empty [`SessionControls`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `control` under `id`, replacing any previous one.
+    pub fn insert(&self, id: SessionId, control: SessionControl) {
+        self.lock().insert(id, control);
+    }
+
+    /// Unregisters the session `id`.
+    pub fn remove(&self, id: &SessionId) -> Option<SessionControl> {
+        self.lock().remove(id)
+    }
+
+    /// Returns the [`SessionControl`] of the session `id`, if registered.
+    pub fn get(&self, id: &SessionId) -> Option<SessionControl> {
+        self.lock().get(id).cloned()
+    }
+
+    /// Returns the [`SessionStatus`] of every registered session, sorted by
+    /// [`SessionId`].
+    pub fn list(&self) -> Vec<(SessionId, SessionStatus)> {
+        let mut sessions: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(id, control)| (id.clone(), control.status()))
+            .collect();
+        sessions.sort_by_cached_key(|(id, _)| id.to_string());
+        sessions
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FxHashMap<SessionId, SessionControl>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,1272 @@
+use super::{
+    Backend, BusinessReject, BusinessRejectReason, Configure, FixConnection, LlEvent, LlEventLoop,
+    Response, SessionControl, SessionState, SessionStatus, ThrottleAction, ThrottleRejectEvent,
+    Verify, split_body_fields,
+};
+use crate::Dictionary;
+use crate::tagvalue::{DecodeError, Decoder, Message};
+use futures::channel::mpsc;
+use futures::future::{Fuse, FusedFuture};
+use futures::stream::FusedStream;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, FutureExt, Stream, StreamExt};
+use futures_timer::Delay;
+use smallvec::SmallVec;
+use std::collections::VecDeque;
+use std::io;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+
+/// Which side of the Logon handshake a [`Session`] plays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SessionRole {
+    /// Sends the first `Logon <A>`.
+    Initiator,
+    /// Waits for the counterparty's `Logon <A>` and replies to it.
+    Acceptor,
+}
+
+/// Errors that terminate a [`Session`].
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    /// The transport failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// The counterparty sent data that can't be decoded as FIX.
+    #[error("Invalid inbound message: {0}")]
+    Decode(#[from] DecodeError),
+    /// The counterparty stopped responding, even to `TestRequest <1>`.
+    #[error("The counterparty missed the heartbeat deadline")]
+    Timeout,
+    /// The transport or the session was closed without a Logout handshake.
+    #[error("The session was closed unexpectedly")]
+    Closed,
+    /// [`SessionHandle::send`] was given a malformed message body.
+    #[error("Invalid message body: {0}")]
+    InvalidBody(String),
+    /// The session was started outside of
+    /// [`Configure::is_session_time`].
+    #[error("Outside of the session schedule")]
+    OutsideSessionTime,
+    /// The session was started while disabled through
+    /// [`SessionControl::disable`].
+    #[error("The session is disabled")]
+    Disabled,
+}
+
+#[derive(Debug)]
+pub(super) enum Command {
+    Send { msg_type: Vec<u8>, body: Vec<u8> },
+    BusinessReject(BusinessReject),
+    Logout { text: String },
+    Reset,
+    ResendRequest { begin: u64, end: u64 },
+    SetNextSenderMsgSeqNum(u64),
+    SetNextTargetMsgSeqNum(u64),
+}
+
+enum Step<'a> {
+    Event(Option<LlEvent<'a>>),
+    Command(Option<Command>),
+    EndOfWindow,
+    Throttle,
+}
+
+/// Drives a [`FixConnection`] over an async transport.
+///
+/// [`Session::run`] reads and decodes inbound messages, feeds them to the
+/// [`FixConnection`], writes its replies, fires heartbeats and test requests
+/// and performs the Logon and Logout handshakes. The application interacts with
+/// the running session through the [`SessionHandle`] returned by
+/// [`Session::new`].
+///
+/// The [`SessionSchedule`](super::SessionSchedule) of the configuration is
+/// enforced: the session logs out when its window ends, and sequence numbers
+/// start over from 1 once a window is over.
+#[derive(Debug)]
+pub struct Session<B, C, V> {
+    connection: FixConnection<B, C, V>,
+    role: SessionRole,
+    dictionary: Dictionary,
+    commands: mpsc::UnboundedReceiver<Command>,
+    inbound: mpsc::UnboundedSender<Vec<u8>>,
+    /// Application messages waiting for the Logon handshake to complete.
+    pending: VecDeque<(Vec<u8>, Vec<u8>)>,
+    /// Whether the last call to [`Session::run_split`] completed the Logon
+    /// handshake.
+    established: bool,
+    /// Start of the schedule window the current sequence numbers belong to.
+    window_start: Option<SystemTime>,
+    /// Whether sequence numbers must be reset once the session is over.
+    reset_pending: bool,
+    /// Whether the last run ended with such a reset.
+    was_reset: bool,
+    /// Shared with every [`SessionControl`].
+    status: Arc<Mutex<SessionStatus>>,
+}
+
+impl<B, C, V> Session<B, C, V>
+where
+    B: Backend,
+    C: Configure,
+    V: Verify,
+{
+    /// Creates a new [`Session`] and the [`SessionHandle`] that controls it.
+    /// `dictionary` is used to decode inbound messages and, unless
+    /// `connection` already has one, to validate them.
+    pub fn new(
+        mut connection: FixConnection<B, C, V>,
+        role: SessionRole,
+        dictionary: Dictionary,
+    ) -> (Self, SessionHandle) {
+        if connection.dictionary().is_none() {
+            connection.set_dictionary(dictionary.clone());
+        }
+        let (commands_tx, commands_rx) = mpsc::unbounded();
+        let (inbound_tx, inbound_rx) = mpsc::unbounded();
+        let status = Arc::new(Mutex::new(SessionStatus {
+            seq_numbers: connection.seq_numbers(),
+            ..SessionStatus::default()
+        }));
+        let session = Self {
+            connection,
+            role,
+            dictionary,
+            commands: commands_rx,
+            inbound: inbound_tx,
+            pending: VecDeque::new(),
+            established: false,
+            window_start: None,
+            reset_pending: false,
+            was_reset: false,
+            status: status.clone(),
+        };
+        let handle = SessionHandle {
+            control: SessionControl {
+                commands: commands_tx,
+                status,
+            },
+            inbound: inbound_rx,
+        };
+        (session, handle)
+    }
+
+    /// Returns a new [`SessionHandle`] sharing `control`, which must belong to
+    /// `self`, e.g. for the next transport of an accepted session. Inbound
+    /// application messages only go to the newest handle from now on.
+    pub(super) fn new_handle(&mut self, control: SessionControl) -> SessionHandle {
+        let (inbound_tx, inbound_rx) = mpsc::unbounded();
+        self.inbound = inbound_tx;
+        SessionHandle {
+            control,
+            inbound: inbound_rx,
+        }
+    }
+
+    /// Returns an immutable reference to the underlying [`FixConnection`].
+    pub fn connection(&self) -> &FixConnection<B, C, V> {
+        &self.connection
+    }
+
+    /// Returns a mutable reference to the underlying [`FixConnection`].
+    pub fn connection_mut(&mut self) -> &mut FixConnection<B, C, V> {
+        &mut self.connection
+    }
+
+    /// Returns the [`SessionRole`] of `self`.
+    pub fn role(&self) -> SessionRole {
+        self.role
+    }
+
+    /// Returns `false` while the session is disabled through
+    /// [`SessionControl::disable`].
+    pub fn is_enabled(&self) -> bool {
+        self.lock_status().enabled
+    }
+
+    /// Returns `true` if the last run of `self` logged out to reset sequence
+    /// numbers, as requested through [`SessionControl::reset_seq_numbers`].
+    pub fn was_reset(&self) -> bool {
+        self.was_reset
+    }
+
+    /// Returns `true` if the current or last run of `self` completed the
+    /// Logon handshake.
+    pub fn was_established(&self) -> bool {
+        self.established
+    }
+
+    /// Runs the session over `stream` until it's logged out or the transport
+    /// fails. A completed Logout handshake returns `Ok(())`.
+    pub async fn run<S>(&mut self, stream: S) -> Result<(), SessionError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (reader, writer) = stream.split();
+        self.run_split(reader, writer).await
+    }
+
+    /// Like [`Session::run`], but with separate read and write halves.
+    pub async fn run_split<R, W>(&mut self, reader: R, mut writer: W) -> Result<(), SessionError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        if self.commands.is_terminated() {
+            // The handle was dropped during a previous run.
+            return Ok(());
+        }
+        let now = self.connection.clock().system_time();
+        if !self.connection.config().is_session_time(now) {
+            writer.close().await.ok();
+            return Err(SessionError::OutsideSessionTime);
+        }
+        if !self.is_enabled() {
+            writer.close().await.ok();
+            return Err(SessionError::Disabled);
+        }
+        self.connection
+            .set_session_state(SessionState::Disconnected);
+        self.established = false;
+        if let Some(seq_numbers) = self.connection.backend().stored_seq_numbers() {
+            self.connection.set_seq_numbers(seq_numbers);
+        }
+        self.roll_schedule_window(now);
+        let window_end = self
+            .connection
+            .config()
+            .schedule()
+            .window(now)
+            .map(|window| window.end);
+
+        let result = self.drive(reader, &mut writer, window_end).await;
+        self.roll_schedule_window(self.connection.clock().system_time());
+        self.was_reset = std::mem::take(&mut self.reset_pending);
+        if self.was_reset {
+            log::info!("Resetting sequence numbers on request");
+            self.connection.reset_seq_numbers();
+        } else if self.connection.config().reset_on_disconnect() {
+            log::info!("Resetting sequence numbers on disconnect");
+            self.connection.reset_seq_numbers();
+        }
+        self.publish_status(None);
+        result
+    }
+
+    async fn drive<R, W>(
+        &mut self,
+        reader: R,
+        writer: &mut W,
+        window_end: Option<SystemTime>,
+    ) -> Result<(), SessionError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let new_decoder = || {
+            let mut decoder = Decoder::new(self.dictionary.clone());
+            for (appl_ver_id, dictionary) in self.connection.application_dictionaries() {
+                decoder.add_application_dictionary(appl_ver_id, dictionary.clone());
+            }
+            decoder
+        };
+        let mut queued_decoder = new_decoder();
+        let mut event_loop = LlEventLoop::new(
+            new_decoder().streaming(SmallVec::new()),
+            reader,
+            self.connection.heartbeat(),
+        );
+        event_loop.set_clock(self.connection.clock().clone());
+        let mut end_of_window = match window_end {
+            Some(end) => {
+                let clock = self.connection.clock();
+                clock
+                    .sleep(end.duration_since(clock.system_time()).unwrap_or_default())
+                    .fuse()
+            }
+            None => Fuse::terminated(),
+        };
+        // Fires when application messages held back by the throttle can go
+        // out.
+        let mut throttle_timer = Fuse::terminated();
+        if self.role == SessionRole::Initiator {
+            let logon = self.connection.on_logon_is_due();
+            write(writer, logon).await?;
+        }
+
+        let mut last_sent = self.connection.last_sent_time();
+        loop {
+            self.publish_status(None);
+            // Heartbeats are only due after a whole interval of silence.
+            if self.connection.last_sent_time() != last_sent {
+                last_sent = self.connection.last_sent_time();
+                event_loop.ping_outbound();
+            }
+            // With `ThrottleAction::Delay`, handles can't queue up more
+            // messages until the throttle lets the first one go.
+            let delayed = !throttle_timer.is_terminated()
+                && self
+                    .connection
+                    .throttle()
+                    .is_some_and(|throttle| throttle.action() == ThrottleAction::Delay);
+            let mut next_command = if delayed {
+                Fuse::terminated()
+            } else {
+                self.commands.next().fuse()
+            };
+            let step = futures::select! {
+                event = event_loop.next_event().fuse() => Step::Event(event),
+                command = next_command => Step::Command(command),
+                () = end_of_window => Step::EndOfWindow,
+                () = throttle_timer => Step::Throttle,
+            };
+            match step {
+                Step::Command(Some(Command::Send { msg_type, body })) => {
+                    self.pending.push_back((msg_type, body));
+                    if let Some(wait) = self.flush_pending(writer).await? {
+                        throttle_timer = Delay::new(wait).fuse();
+                    }
+                }
+                Step::Command(Some(Command::BusinessReject(reject))) => {
+                    if self.connection.session_state().is_logged_on() {
+                        let reject = self.connection.business_reject(&reject);
+                        write(writer, reject).await?;
+                    } else {
+                        log::warn!(
+                            "Ignoring a BusinessMessageReject <j> outside of a logged on session"
+                        );
+                    }
+                }
+                Step::Command(Some(Command::Reset)) => {
+                    if !self.connection.session_state().is_logged_on() {
+                        self.connection.reset_seq_numbers();
+                    } else {
+                        self.reset_pending = true;
+                        if !self.logout(writer, "Sequence numbers reset").await? {
+                            return Ok(());
+                        }
+                    }
+                }
+                Step::Command(Some(Command::ResendRequest { begin, end })) => {
+                    if self.connection.session_state().is_logged_on() {
+                        let resend_request = self.connection.request_resend(begin, end);
+                        write(writer, resend_request).await?;
+                    } else {
+                        log::warn!("Ignoring a ResendRequest <2> outside of a logged on session");
+                    }
+                }
+                Step::Command(Some(Command::SetNextSenderMsgSeqNum(seq_num))) => {
+                    self.connection.set_next_sender_msg_seq_num(seq_num);
+                }
+                Step::Command(Some(Command::SetNextTargetMsgSeqNum(seq_num))) => {
+                    self.connection.set_next_target_msg_seq_num(seq_num);
+                }
+                Step::Throttle => {
+                    if let Some(wait) = self.flush_pending(writer).await? {
+                        throttle_timer = Delay::new(wait).fuse();
+                    }
+                }
+                Step::Command(Some(Command::Logout { text })) => {
+                    if !self.logout(writer, &text).await? {
+                        return Ok(());
+                    }
+                }
+                Step::Command(None) => {
+                    // Every handle is gone, nobody is listening anymore.
+                    if !self.logout(writer, "").await? {
+                        return Ok(());
+                    }
+                }
+                Step::EndOfWindow => {
+                    log::info!("The session schedule window is over, logging out");
+                    if !self.logout(writer, "End of session").await? {
+                        return Ok(());
+                    }
+                }
+                Step::Event(None) => {
+                    return if self.connection.session_state().is_disconnected() {
+                        Ok(())
+                    } else {
+                        self.connection
+                            .set_session_state(SessionState::Disconnected);
+                        Err(SessionError::Closed)
+                    };
+                }
+                Step::Event(Some(LlEvent::Message(message))) => {
+                    self.publish_status(Some(self.connection.clock().system_time()));
+                    let was_logged_on = self.connection.session_state().is_logged_on();
+                    if let ControlFlow::Break(result) = self.process(message, writer).await {
+                        return result;
+                    }
+                    // Messages received beyond a sequence gap that is now
+                    // filled.
+                    while let Some(queued) = self.connection.pop_queued_message() {
+                        let message = match queued_decoder.decode(queued.as_slice()) {
+                            Ok(message) => message,
+                            Err(err) => {
+                                log::error!("Failed to decode a queued message: {err}");
+                                continue;
+                            }
+                        };
+                        if let ControlFlow::Break(result) = self.process(message, writer).await {
+                            return result;
+                        }
+                    }
+                    event_loop.ping_heartbeat();
+                    let state = self.connection.session_state();
+                    if !was_logged_on && state.is_logged_on() {
+                        self.established = true;
+                        event_loop.set_heartbeat(self.connection.heartbeat());
+                        // Application messages without an `ApplVerID <1128>`
+                        // follow the negotiated `DefaultApplVerID <1137>`.
+                        let appl_ver_id = self
+                            .connection
+                            .counterparty_appl_ver_id()
+                            .and_then(|id| std::str::from_utf8(id).ok());
+                        event_loop
+                            .decoder_mut()
+                            .set_default_appl_ver_id(appl_ver_id);
+                        queued_decoder.set_default_appl_ver_id(appl_ver_id);
+                    }
+                    if let Some(wait) = self.flush_pending(writer).await? {
+                        throttle_timer = Delay::new(wait).fuse();
+                    }
+                }
+                Step::Event(Some(LlEvent::BadMessage(err))) => {
+                    self.connection
+                        .set_session_state(SessionState::Disconnected);
+                    return Err(err.into());
+                }
+                Step::Event(Some(LlEvent::IoError(err))) => {
+                    self.connection
+                        .set_session_state(SessionState::Disconnected);
+                    return Err(err.into());
+                }
+                Step::Event(Some(LlEvent::Heartbeat)) => {
+                    if self.connection.session_state().is_logged_on() {
+                        let heartbeat = self.connection.on_heartbeat_is_due();
+                        write(writer, heartbeat).await?;
+                    }
+                }
+                Step::Event(Some(LlEvent::TestRequest)) => {
+                    if self.connection.session_state().is_logged_on() {
+                        let test_request = self.connection.on_test_request_is_due();
+                        write(writer, test_request).await?;
+                    }
+                }
+                Step::Event(Some(LlEvent::Logout)) => {
+                    log::error!("The counterparty stopped responding, logging out");
+                    let logout = self.connection.on_heartbeat_timeout();
+                    if !logout.is_empty() {
+                        write(writer, logout).await.ok();
+                    }
+                    writer.close().await.ok();
+                    self.connection
+                        .set_session_state(SessionState::Disconnected);
+                    return Err(SessionError::Timeout);
+                }
+            }
+        }
+    }
+
+    /// Runs the session over a Tokio [`TcpStream`](tokio::net::TcpStream).
+    #[cfg(feature = "utils-tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "utils-tokio")))]
+    pub async fn run_tcp(&mut self, stream: tokio::net::TcpStream) -> Result<(), SessionError> {
+        use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+        let (reader, writer) = stream.into_split();
+        self.run_split(reader.compat(), writer.compat_write()).await
+    }
+
+    /// Feeds one inbound message to the [`FixConnection`] and writes its
+    /// reply. Breaks with the outcome of the session once it's over.
+    async fn process<W>(
+        &mut self,
+        message: Message<'_, &[u8]>,
+        writer: &mut W,
+    ) -> ControlFlow<Result<(), SessionError>>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let was_logging_out = self.connection.session_state() == SessionState::LogoutPending;
+        match self.connection.on_inbound_message(message) {
+            Response::OutboundBytes(bytes) => {
+                if let Err(err) = write(writer, bytes).await {
+                    return ControlFlow::Break(Err(err.into()));
+                }
+            }
+            Response::Application(message) => {
+                if self
+                    .inbound
+                    .unbounded_send(message.as_bytes().to_vec())
+                    .is_err()
+                {
+                    // The handle was dropped already.
+                    let reject = BusinessReject::of(
+                        message.as_bytes(),
+                        BusinessRejectReason::ApplicationNotAvailable,
+                    )
+                    .map(|reject| reject.with_text("Application not available"));
+                    if let Some(reject) = reject {
+                        let reject = self.connection.business_reject(&reject);
+                        if let Err(err) = write(writer, reject).await {
+                            return ControlFlow::Break(Err(err.into()));
+                        }
+                    }
+                }
+            }
+            Response::TerminateTransport => {
+                writer.close().await.ok();
+                return ControlFlow::Break(if was_logging_out {
+                    Ok(())
+                } else {
+                    Err(SessionError::Closed)
+                });
+            }
+            Response::LogGarbled => {
+                log::warn!("Garbled inbound message: {:?}", message.as_bytes());
+            }
+            Response::None | Response::ResetHeartbeat => {}
+        }
+        if let Some(resend_request) = self.connection.next_resend_request()
+            && let Err(err) = write(writer, resend_request).await
+        {
+            return ControlFlow::Break(Err(err.into()));
+        }
+        if self.connection.session_state().is_disconnected() {
+            writer.close().await.ok();
+            return ControlFlow::Break(Ok(()));
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Resets the sequence numbers if the schedule window they belong to is
+    /// over at `now`.
+    fn roll_schedule_window(&mut self, now: SystemTime) {
+        let window_start = self
+            .connection
+            .config()
+            .schedule()
+            .window(now)
+            .map(|window| window.start);
+        let is_stale = match (self.window_start, window_start) {
+            (Some(_), _) => self.window_start != window_start,
+            // Nothing is known about the sequence numbers restored from the
+            // backend, e.g. after a restart, other than when its store was
+            // created.
+            (None, Some(start)) => self
+                .connection
+                .backend()
+                .creation_time()
+                .is_some_and(|creation_time| creation_time < start),
+            (None, None) => false,
+        };
+        if is_stale {
+            log::info!("New session schedule window, resetting sequence numbers");
+            self.connection.reset_seq_numbers();
+        }
+        self.window_start = window_start;
+    }
+
+    /// Starts the Logout handshake. Returns `false` if there's no established
+    /// session to log out from, in which case the transport is closed.
+    async fn logout<W>(&mut self, writer: &mut W, text: &str) -> Result<bool, SessionError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        match self.connection.session_state() {
+            SessionState::LogoutPending => Ok(true),
+            state if state.is_logged_on() => {
+                let logout = self.connection.initiate_logout(text);
+                write(writer, logout).await?;
+                Ok(true)
+            }
+            _ => {
+                writer.close().await.ok();
+                self.connection
+                    .set_session_state(SessionState::Disconnected);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Updates the [`SessionStatus`] seen by [`SessionControl`]s, including
+    /// the time of the last inbound message if there's a new one.
+    fn publish_status(&self, last_heartbeat: Option<SystemTime>) {
+        let mut status = self.lock_status();
+        status.state = self.connection.session_state();
+        status.seq_numbers = self.connection.seq_numbers();
+        if last_heartbeat.is_some() {
+            status.last_heartbeat = last_heartbeat;
+        }
+    }
+
+    fn lock_status(&self) -> std::sync::MutexGuard<'_, SessionStatus> {
+        self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sends pending application messages while the session allows it.
+    /// Returns how long to wait before trying again if the throttle holds
+    /// messages back. Messages the throttle rejects are reported to the
+    /// [`SessionListener`](super::SessionListener).
+    async fn flush_pending<W>(&mut self, writer: &mut W) -> Result<Option<Duration>, SessionError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        while self
+            .connection
+            .session_state()
+            .can_send_application_messages()
+        {
+            let Some((msg_type, _)) = self.pending.front() else {
+                break;
+            };
+            let wait = self.connection.throttle_delay(msg_type);
+            if !wait.is_zero()
+                && let Some(throttle) = self.connection.throttle()
+            {
+                throttle.on_throttled();
+                match throttle.action() {
+                    ThrottleAction::Queue | ThrottleAction::Delay => return Ok(Some(wait)),
+                    ThrottleAction::Reject => {
+                        log::warn!(
+                            "Dropped a throttled message of type {}",
+                            String::from_utf8_lossy(msg_type)
+                        );
+                        if let Some((msg_type, body)) = self.pending.pop_front() {
+                            let rejected = ThrottleRejectEvent {
+                                msg_type,
+                                body,
+                                wait,
+                            };
+                            self.connection.notify_throttle_reject(&rejected);
+                        }
+                        continue;
+                    }
+                }
+            }
+            let Some((msg_type, body)) = self.pending.pop_front() else {
+                break;
+            };
+            let Some(message) = self.connection.send_app_message(&msg_type, &body) else {
+                return Err(SessionError::InvalidBody(
+                    String::from_utf8_lossy(&body).into_owned(),
+                ));
+            };
+            write(writer, message).await?;
+        }
+        Ok(None)
+    }
+}
+
+async fn write<W>(writer: &mut W, bytes: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(bytes).await?;
+    writer.flush().await
+}
+
+/// Application-side handle of a running [`Session`].
+///
+/// Inbound application messages are received as raw bytes through
+/// [`SessionHandle::recv`] or the [`Stream`] implementation. Dropping the
+/// handle logs the session out.
+#[derive(Debug)]
+pub struct SessionHandle {
+    control: SessionControl,
+    inbound: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+impl SessionHandle {
+    /// Queues an application message of type `msg_type` for sending. `body`
+    /// must follow the rules of [`split_body_fields`]. Messages sent before the
+    /// Logon handshake completes are held back until it does.
+    pub fn send(&self, msg_type: &[u8], body: &[u8]) -> Result<(), SessionError> {
+        if msg_type.is_empty() || split_body_fields(body).is_none() {
+            return Err(SessionError::InvalidBody(
+                String::from_utf8_lossy(body).into_owned(),
+            ));
+        }
+        self.control.command(Command::Send {
+            msg_type: msg_type.to_vec(),
+            body: body.to_vec(),
+        })
+    }
+
+    /// Sends a `BusinessMessageReject <j>`, e.g. for an application message
+    /// received through [`SessionHandle::recv`] that the application doesn't
+    /// support; see [`BusinessReject::of`]. Ignored unless the session is
+    /// logged on.
+    pub fn business_reject(&self, reject: BusinessReject) -> Result<(), SessionError> {
+        self.control.command(Command::BusinessReject(reject))
+    }
+
+    /// Starts the Logout handshake with an optional `Text <58>`. The
+    /// [`Session`] returns once the counterparty confirms.
+    pub fn logout(&self, text: &str) -> Result<(), SessionError> {
+        self.control.logout(text)
+    }
+
+    /// Sets the `MsgSeqNum <34>` of the next outbound message. See
+    /// [`FixConnection::set_next_sender_msg_seq_num`].
+    pub fn set_next_sender_msg_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.control.set_next_sender_msg_seq_num(seq_num)
+    }
+
+    /// Sets the `MsgSeqNum <34>` expected from the next inbound message. See
+    /// [`FixConnection::set_next_target_msg_seq_num`].
+    pub fn set_next_target_msg_seq_num(&self, seq_num: u64) -> Result<(), SessionError> {
+        self.control.set_next_target_msg_seq_num(seq_num)
+    }
+
+    /// Returns the latest [`SessionStatus`] of the [`Session`].
+    pub fn status(&self) -> SessionStatus {
+        self.control.status()
+    }
+
+    /// Returns a [`SessionControl`] for the [`Session`], e.g. to register it
+    /// with an admin service.
+    pub fn control(&self) -> SessionControl {
+        self.control.clone()
+    }
+
+    /// Waits for the next inbound application message. Returns [`None`] once
+    /// the [`Session`] has stopped.
+    pub async fn recv(&mut self) -> Option<Vec<u8>> {
+        self.inbound.next().await
+    }
+}
+
+impl Stream for SessionHandle {
+    type Item = Vec<u8>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inbound.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field_types::Time;
+    use crate::session::backends::{FileBackend, MemoryBackend};
+    use crate::session::test_utils::{order, scheduled_session, session};
+    use crate::session::{
+        Clock, Config, MockClock, NoOpVerifier, RateLimit, SeqNumbers, SessionListener,
+        SessionSchedule, SessionTz, Throttle, ThrottleMetrics,
+    };
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    /// A daily UTC schedule from the time of day of `start` to that of `end`.
+    fn daily_schedule(start: SystemTime, end: SystemTime) -> SessionSchedule {
+        let time_of_day = |time: SystemTime| {
+            let millis = time.duration_since(UNIX_EPOCH).unwrap().as_millis() % 86_400_000;
+            let millis = millis as u32;
+            Time::from_hmsm(
+                millis / 3_600_000,
+                millis / 60_000 % 60,
+                millis / 1000 % 60,
+                millis % 1000,
+            )
+            .unwrap()
+        };
+        SessionSchedule::Daily {
+            start: time_of_day(start),
+            end: time_of_day(end),
+            tz: SessionTz::UTC,
+        }
+    }
+
+    #[tokio::test]
+    async fn initiator_and_acceptor_exchange_messages() {
+        let (initiator_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut initiator, initiator_handle) = session("INIT", "ACC", SessionRole::Initiator);
+        let (mut acceptor, mut acceptor_handle) = session("ACC", "INIT", SessionRole::Acceptor);
+
+        let app = async {
+            // Queued until the Logon handshake completes.
+            initiator_handle.send(b"D", &order("ORDER")).unwrap();
+            let message = acceptor_handle.recv().await.unwrap();
+            assert!(
+                message
+                    .windows(b"\x0111=ORDER\x01".len())
+                    .any(|w| w == b"\x0111=ORDER\x01")
+            );
+            initiator_handle.logout("done").unwrap();
+            (initiator_handle, acceptor_handle)
+        };
+        let (initiator_result, acceptor_result, _handles) = tokio::join!(
+            initiator.run(initiator_io.compat()),
+            acceptor.run(acceptor_io.compat()),
+            app
+        );
+        initiator_result.unwrap();
+        acceptor_result.unwrap();
+        assert!(initiator.connection().session_state().is_disconnected());
+        assert!(acceptor.connection().session_state().is_disconnected());
+        assert_eq!(initiator.connection().seq_numbers().next_outbound(), 4);
+    }
+
+    /// Records the bodies of messages dropped by the throttle.
+    #[derive(Debug, Clone, Default)]
+    struct ThrottleRejects(Arc<std::sync::Mutex<Vec<Vec<u8>>>>);
+
+    impl SessionListener for ThrottleRejects {
+        fn on_throttle_reject(&mut self, rejected: &ThrottleRejectEvent) {
+            self.0.lock().unwrap().push(rejected.body.clone());
+        }
+    }
+
+    async fn throttled_exchange(
+        action: ThrottleAction,
+    ) -> (Vec<Vec<u8>>, Arc<ThrottleMetrics>, ThrottleRejects) {
+        let (initiator_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut initiator, initiator_handle) = session("INIT", "ACC", SessionRole::Initiator);
+        let (mut acceptor, mut acceptor_handle) = session("ACC", "INIT", SessionRole::Acceptor);
+        let throttle = Throttle::new(action).with_msg_type_limit(
+            b"D",
+            RateLimit {
+                max_messages: 1,
+                per: Duration::from_millis(50),
+            },
+        );
+        let metrics = throttle.metrics();
+        let rejects = ThrottleRejects::default();
+        initiator.connection_mut().set_throttle(throttle);
+        initiator
+            .connection_mut()
+            .set_listener(Box::new(rejects.clone()));
+
+        let app = async {
+            for cl_ord_id in ["A", "B", "C"] {
+                initiator_handle.send(b"D", &order(cl_ord_id)).unwrap();
+            }
+            initiator_handle.send(b"B", b"148=NEWS\x01").unwrap();
+            let mut received = Vec::new();
+            while let Some(message) = acceptor_handle.recv().await {
+                let news = message.windows(5).any(|w| w == b"148=N");
+                received.push(message);
+                if news {
+                    break;
+                }
+            }
+            initiator_handle.logout("done").unwrap();
+            (initiator_handle, acceptor_handle, received)
+        };
+        let (initiator_result, acceptor_result, (_, _, received)) = tokio::join!(
+            initiator.run(initiator_io.compat()),
+            acceptor.run(acceptor_io.compat()),
+            app
+        );
+        initiator_result.unwrap();
+        acceptor_result.unwrap();
+        (received, metrics, rejects)
+    }
+
+    #[tokio::test]
+    async fn queued_messages_wait_for_the_throttle() {
+        let (received, metrics, _) = throttled_exchange(ThrottleAction::Queue).await;
+        // Unthrottled types still wait behind the queue, in order.
+        assert_eq!(received.len(), 4);
+        assert!(metrics.queued() >= 2);
+        assert_eq!(metrics.rejected(), 0);
+    }
+
+    #[tokio::test]
+    async fn delayed_messages_wait_for_the_throttle() {
+        let (received, metrics, _) = throttled_exchange(ThrottleAction::Delay).await;
+        assert_eq!(received.len(), 4);
+        assert!(metrics.delayed() >= 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_messages_are_dropped_and_reported() {
+        let (received, metrics, rejects) = throttled_exchange(ThrottleAction::Reject).await;
+        assert_eq!(received.len(), 2);
+        assert_eq!(metrics.rejected(), 2);
+        assert_eq!(*rejects.0.lock().unwrap(), [order("B"), order("C")]);
+    }
+
+    #[test]
+    fn send_rejects_header_fields() {
+        let (_session, handle) = session("INIT", "ACC", SessionRole::Initiator);
+        assert!(matches!(
+            handle.send(b"D", b"35=D\x01"),
+            Err(SessionError::InvalidBody(_))
+        ));
+        assert!(handle.send(b"D", b"11=ORDER\x01").is_ok());
+    }
+
+    #[tokio::test]
+    async fn logs_out_and_resets_at_end_of_window() {
+        // 2024-01-02 08:00:00 UTC, ten seconds before the window closes.
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_704_182_400));
+        let schedule = daily_schedule(
+            clock.system_time() - Duration::from_secs(3600),
+            clock.system_time() + Duration::from_secs(10),
+        );
+        let (initiator_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut initiator, initiator_handle) =
+            scheduled_session("INIT", "ACC", SessionRole::Initiator, schedule);
+        let (mut acceptor, mut acceptor_handle) =
+            scheduled_session("ACC", "INIT", SessionRole::Acceptor, schedule);
+        initiator
+            .connection_mut()
+            .set_clock(Arc::new(clock.clone()));
+        acceptor.connection_mut().set_clock(Arc::new(clock.clone()));
+
+        let app = async {
+            initiator_handle.send(b"D", &order("ORDER")).unwrap();
+            let message = acceptor_handle.recv().await;
+            clock.advance(Duration::from_secs(10));
+            (message, initiator_handle, acceptor_handle)
+        };
+        let (initiator_result, acceptor_result, (message, _, _)) = tokio::join!(
+            initiator.run(initiator_io.compat()),
+            acceptor.run(acceptor_io.compat()),
+            app
+        );
+        initiator_result.unwrap();
+        acceptor_result.unwrap();
+        assert!(message.is_some());
+        assert!(initiator.was_established());
+        let seq_numbers = initiator.connection().seq_numbers();
+        assert_eq!(
+            (seq_numbers.next_inbound(), seq_numbers.next_outbound()),
+            (1, 1)
+        );
+        let seq_numbers = acceptor.connection().seq_numbers();
+        assert_eq!(
+            (seq_numbers.next_inbound(), seq_numbers.next_outbound()),
+            (1, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_to_run_outside_of_window() {
+        let now = SystemTime::now();
+        let schedule = daily_schedule(
+            now + Duration::from_secs(3600),
+            now + Duration::from_secs(7200),
+        );
+        let (io, _peer) = tokio::io::duplex(1024);
+        let (mut initiator, _handle) =
+            scheduled_session("INIT", "ACC", SessionRole::Initiator, schedule);
+        assert!(matches!(
+            initiator.run(io.compat()).await,
+            Err(SessionError::OutsideSessionTime)
+        ));
+        assert!(!initiator.was_established());
+    }
+
+    #[tokio::test]
+    async fn schedule_follows_the_clock() {
+        // 2024-01-02 08:00:00 UTC, an hour before the window opens.
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_704_182_400));
+        let schedule = daily_schedule(
+            clock.system_time() + Duration::from_secs(3600),
+            clock.system_time() + Duration::from_secs(8 * 3600),
+        );
+        let (mut initiator, initiator_handle) =
+            scheduled_session("INIT", "ACC", SessionRole::Initiator, schedule);
+        let (mut acceptor, mut acceptor_handle) =
+            scheduled_session("ACC", "INIT", SessionRole::Acceptor, schedule);
+        initiator
+            .connection_mut()
+            .set_clock(Arc::new(clock.clone()));
+        acceptor.connection_mut().set_clock(Arc::new(clock.clone()));
+        let (io, _peer) = tokio::io::duplex(1024);
+        assert!(matches!(
+            initiator.run(io.compat()).await,
+            Err(SessionError::OutsideSessionTime)
+        ));
+
+        clock.advance(Duration::from_secs(2 * 3600));
+        let (initiator_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let app = async {
+            initiator_handle.send(b"D", &order("ORDER")).unwrap();
+            let message = acceptor_handle.recv().await.unwrap();
+            initiator_handle.logout("done").unwrap();
+            message
+        };
+        let (initiator_result, acceptor_result, message) = tokio::join!(
+            initiator.run(initiator_io.compat()),
+            acceptor.run(acceptor_io.compat()),
+            app
+        );
+        initiator_result.unwrap();
+        acceptor_result.unwrap();
+        let sending_time = b"\x0152=20240102-10:00:00";
+        assert!(
+            message
+                .windows(sending_time.len())
+                .any(|w| w == sending_time)
+        );
+    }
+
+    #[tokio::test]
+    async fn resets_seq_numbers_stored_in_an_earlier_window() {
+        let path = std::env::temp_dir().join("test_driver_stale_window.journal");
+        let seq_num_path = path.with_extension("journal.seqnums");
+        let session_path = path.with_extension("journal.session");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&seq_num_path);
+        let _ = std::fs::remove_file(&session_path);
+        let backend = FileBackend::new("INIT", "ACC", &path).unwrap();
+        backend
+            .set_seq_numbers(SeqNumbers {
+                next_inbound: 5,
+                next_outbound: 5,
+            })
+            .unwrap();
+
+        let run_at = |now: SystemTime| {
+            let mut config = Config::default();
+            config.schedule = daily_schedule(
+                now - Duration::from_secs(3600),
+                now + Duration::from_secs(3600),
+            );
+            let mut connection = FixConnection::new(backend.clone(), config, NoOpVerifier);
+            connection.set_clock(Arc::new(MockClock::new(now)));
+            let (mut initiator, _handle) = Session::new(
+                connection,
+                SessionRole::Initiator,
+                Dictionary::fix44().unwrap(),
+            );
+            async move {
+                let (io, peer) = tokio::io::duplex(1024);
+                drop(peer);
+                assert!(initiator.run(io.compat()).await.is_err());
+                initiator.connection().seq_numbers().next_outbound()
+            }
+        };
+        // Counting the Logon <A> sent before the peer hangs up. The store was
+        // created within the current window.
+        assert_eq!(run_at(SystemTime::now()).await, 6);
+        // The store predates the window, e.g. after a restart the next day.
+        let next_day = SystemTime::now() + Duration::from_secs(86_400);
+        assert_eq!(run_at(next_day).await, 2);
+        assert_eq!(backend.seq_numbers().unwrap().next_outbound(), 2);
+
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(seq_num_path);
+        let _ = std::fs::remove_file(session_path);
+    }
+
+    #[tokio::test]
+    async fn resets_on_disconnect() {
+        let mut config = Config::default();
+        config.reset_on_disconnect = true;
+        let connection =
+            FixConnection::new(MemoryBackend::new("INIT", "ACC"), config, NoOpVerifier);
+        let (mut initiator, _handle) = Session::new(
+            connection,
+            SessionRole::Initiator,
+            Dictionary::fix44().unwrap(),
+        );
+        initiator.connection_mut().set_seq_numbers(SeqNumbers {
+            next_inbound: 5,
+            next_outbound: 5,
+        });
+        let (io, peer) = tokio::io::duplex(1024);
+        drop(peer);
+        assert!(initiator.run(io.compat()).await.is_err());
+        let seq_numbers = initiator.connection().seq_numbers();
+        assert_eq!(
+            (seq_numbers.next_inbound(), seq_numbers.next_outbound()),
+            (1, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn control_reports_status_and_resets_seq_numbers() {
+        let (initiator_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut initiator, initiator_handle) = session("INIT", "ACC", SessionRole::Initiator);
+        let (mut acceptor, mut acceptor_handle) = session("ACC", "INIT", SessionRole::Acceptor);
+        let control = initiator_handle.control();
+        assert_eq!(control.status().state, SessionState::Disconnected);
+
+        let app = async {
+            initiator_handle.send(b"D", &order("ORDER")).unwrap();
+            acceptor_handle.recv().await.unwrap();
+            let status = acceptor_handle.status();
+            assert_eq!(status.state, SessionState::Active);
+            assert_eq!(status.seq_numbers.next_inbound(), 3);
+            assert!(status.last_heartbeat.is_some());
+            control.reset_seq_numbers().unwrap();
+            (initiator_handle, acceptor_handle)
+        };
+        let (initiator_result, acceptor_result, _handles) = tokio::join!(
+            initiator.run(initiator_io.compat()),
+            acceptor.run(acceptor_io.compat()),
+            app
+        );
+        initiator_result.unwrap();
+        acceptor_result.unwrap();
+        assert!(initiator.was_reset());
+        assert!(!acceptor.was_reset());
+        let status = control.status();
+        assert_eq!(status.state, SessionState::Disconnected);
+        assert_eq!(
+            (
+                status.seq_numbers.next_inbound(),
+                status.seq_numbers.next_outbound()
+            ),
+            (1, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_sessions_refuse_to_run() {
+        let (io, _peer) = tokio::io::duplex(1024);
+        let (mut initiator, handle) = session("INIT", "ACC", SessionRole::Initiator);
+        let control = handle.control();
+        control.disable("maintenance").unwrap();
+        assert!(!handle.status().enabled);
+        assert!(matches!(
+            initiator.run(io.compat()).await,
+            Err(SessionError::Disabled)
+        ));
+
+        control.enable();
+        assert!(initiator.is_enabled());
+    }
+
+    /// Encodes a message from `INIT` to `ACC`.
+    fn encode(seq_num: u64, msg_type: &[u8], fields: &[(u32, &str)]) -> Vec<u8> {
+        use crate::SetField;
+        use crate::field_types::Timestamp;
+
+        let mut buffer = Vec::new();
+        let mut encoder = crate::tagvalue::Encoder::new();
+        let mut msg = encoder.start_message(b"FIX.4.4", &mut buffer, msg_type);
+        msg.set(49, "INIT");
+        msg.set(56, "ACC");
+        msg.set(34, seq_num);
+        msg.set(52, Timestamp::utc_now());
+        for (tag, value) in fields {
+            msg.set(*tag, *value);
+        }
+        msg.done();
+        buffer
+    }
+
+    #[tokio::test]
+    async fn heartbeats_and_test_requests_a_silent_counterparty() {
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        // Sent before the clock stops, so that it isn't from the future.
+        let logon = encode(1, b"A", &[(98, "0"), (108, "1")]);
+        let clock = MockClock::default();
+        let (client_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut acceptor, _handle) = session("ACC", "INIT", SessionRole::Acceptor);
+        acceptor.connection_mut().set_clock(Arc::new(clock.clone()));
+        let client = async {
+            let (mut reader, mut writer) = tokio::io::split(client_io);
+            writer.write_all(&logon).await.unwrap();
+            let mut received = Vec::new();
+            loop {
+                // Time only moves on once the acceptor has nothing to say.
+                let read = reader.read_buf(&mut received);
+                match tokio::time::timeout(Duration::from_millis(10), read).await {
+                    Ok(Ok(0)) => break,
+                    Ok(read) => {
+                        read.unwrap();
+                    }
+                    Err(_) => clock.advance(Duration::from_millis(500)),
+                }
+            }
+            (received, writer)
+        };
+        let (result, (received, _writer)) =
+            tokio::join!(acceptor.run(acceptor_io.compat()), client);
+        assert!(matches!(result, Err(SessionError::Timeout)));
+        let contains = |field: &[u8]| received.windows(field.len()).any(|w| w == field);
+        assert!(contains(b"\x0135=0\x01"));
+        assert!(contains(b"\x0135=1\x01"));
+        assert!(contains(b"\x01112=TEST-1\x01"));
+        assert!(contains(b"\x0135=5\x01"));
+        assert_eq!(acceptor.connection().pending_test_request(), Some("TEST-1"));
+    }
+
+    #[tokio::test]
+    async fn business_rejects_messages_nobody_receives() {
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        let (client_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut acceptor, handle) = session("ACC", "INIT", SessionRole::Acceptor);
+        // Keeps the session running without anybody receiving messages.
+        let _control = handle.control();
+        drop(handle);
+        let client = async {
+            let (mut reader, mut writer) = tokio::io::split(client_io);
+            let order = [
+                (11, "ORDER"),
+                (54, "1"),
+                (60, "20240102-14:00:00"),
+                (40, "1"),
+            ];
+            for message in [
+                encode(1, b"A", &[(98, "0"), (108, "30")]),
+                encode(2, b"D", &order),
+                encode(3, b"5", &[]),
+            ] {
+                writer.write_all(&message).await.unwrap();
+            }
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).await.unwrap();
+            (received, writer)
+        };
+        let (result, (received, _writer)) =
+            tokio::join!(acceptor.run(acceptor_io.compat()), client);
+        assert!(result.is_ok());
+        let contains = |field: &[u8]| received.windows(field.len()).any(|w| w == field);
+        assert!(contains(b"\x0135=j\x01"));
+        assert!(contains(b"\x0145=2\x01372=D\x01380=4\x01"));
+    }
+
+    #[tokio::test]
+    async fn replays_messages_queued_beyond_a_gap() {
+        use tokio::io::AsyncWriteExt as _;
+
+        let (client_io, acceptor_io) = tokio::io::duplex(64 * 1024);
+        let (mut acceptor, mut handle) = session("ACC", "INIT", SessionRole::Acceptor);
+        let client = async {
+            // Keep the read half open until the acceptor is done.
+            let (reader, mut writer) = tokio::io::split(client_io);
+            for message in [
+                encode(1, b"A", &[(98, "0"), (108, "30")]),
+                encode(
+                    3,
+                    b"D",
+                    &[(11, "3"), (54, "1"), (60, "20240102-14:00:00"), (40, "1")],
+                ),
+                encode(
+                    2,
+                    b"D",
+                    &[(11, "2"), (54, "1"), (60, "20240102-14:00:00"), (40, "1")],
+                ),
+            ] {
+                writer.write_all(&message).await.unwrap();
+            }
+            let first = handle.recv().await.unwrap();
+            let second = handle.recv().await.unwrap();
+            writer.write_all(&encode(4, b"5", &[])).await.unwrap();
+            (first, second, reader)
+        };
+        let (result, (first, second, _reader)) =
+            tokio::join!(acceptor.run(acceptor_io.compat()), client);
+        result.unwrap();
+        let contains =
+            |message: &[u8], field: &[u8]| message.windows(field.len()).any(|w| w == field);
+        assert!(contains(&first, b"\x0111=2\x01"));
+        assert!(contains(&second, b"\x0111=3\x01"));
+        assert_eq!(acceptor.connection().seq_numbers().next_inbound(), 5);
+    }
+}
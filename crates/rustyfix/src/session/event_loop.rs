@@ -1,10 +1,12 @@
+use super::{Clock, SystemClock};
 use crate::StreamingDecoder;
-use crate::tagvalue::{DecodeError, DecoderStreaming, Message};
+use crate::tagvalue::{DecodeError, Decoder, DecoderStreaming, Message};
+use futures::future::Fuse;
 use futures::{AsyncRead, AsyncReadExt, FutureExt, select};
-use futures_timer::Delay;
 use quanta::Instant;
 use smallvec::SmallVec;
 use std::io;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Asynchronous, executor-agnostic low-level event loop for FIX connectors.
@@ -12,16 +14,28 @@ use std::time::Duration;
 /// This event loop allows FIX connectors to delegate event-tracking logic to a
 /// single entity. This event loop keeps track of such events within a FIX
 /// session. See [`LlEvent`] for more information.
+///
+/// [`LlEventLoop::next_event`] is cancel-safe: if the returned future is
+/// dropped before completion (e.g. because it lost a `select!` race), no
+/// input is lost and the next call picks up where the previous one left off.
 #[derive(Debug)]
 pub struct LlEventLoop<I> {
     decoder: DecoderStreaming<SmallVec<[u8; 1024]>>,
     input: I,
+    /// Number of bytes at the start of the decoder's buffer that hold actual
+    /// input.
+    buf_filled_len: usize,
+    /// The last returned message is still borrowed from the decoder, which
+    /// must be cleared before reading more data.
+    message_pending: bool,
     heartbeat: Duration,
     heartbeat_soft_tolerance: Duration,
     heartbeat_hard_tolerance: Duration,
     last_reset: Instant,
     last_heartbeat: Instant,
+    test_request_sent: bool,
     is_alive: bool,
+    clock: Arc<dyn Clock>,
 }
 
 impl<I> LlEventLoop<I>
@@ -37,18 +51,46 @@ where
     ) -> Self {
         let heartbeat_soft_tolerance = heartbeat * 2;
         let heartbeat_hard_tolerance = heartbeat * 3;
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
         Self {
             decoder,
             input,
+            buf_filled_len: 0,
+            message_pending: false,
             heartbeat,
             heartbeat_soft_tolerance,
             heartbeat_hard_tolerance,
-            last_reset: Instant::now(),
-            last_heartbeat: Instant::now(),
+            last_reset: clock.now(),
+            last_heartbeat: clock.now(),
+            test_request_sent: false,
             is_alive: true,
+            clock,
         }
     }
 
+    /// Reads the time from `clock` from now on, e.g. a
+    /// [`MockClock`](super::MockClock) in tests. All timers restart.
+    /// [`SystemClock`] by default.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.last_reset = clock.now();
+        self.last_heartbeat = clock.now();
+        self.clock = clock;
+    }
+
+    /// Returns a mutable reference to the [`Decoder`] of inbound messages.
+    pub fn decoder_mut(&mut self) -> &mut Decoder {
+        self.decoder.decoder_mut()
+    }
+
+    /// Changes the heartbeat interval, e.g. after the counterparty proposed a
+    /// different `HeartBtInt <108>` during the handshake. Tolerances are reset
+    /// to their defaults relative to `heartbeat`.
+    pub fn set_heartbeat(&mut self, heartbeat: Duration) {
+        self.heartbeat = heartbeat;
+        self.heartbeat_soft_tolerance = heartbeat * 2;
+        self.heartbeat_hard_tolerance = heartbeat * 3;
+    }
+
     /// How long after a missed `Heartbeat <0>` should we send a `TestRequest
     /// <1>`?
     pub fn set_soft_tolerance(&mut self, soft_tolerance: Duration) {
@@ -60,42 +102,75 @@ where
         self.heartbeat_hard_tolerance = hard_tolerance;
     }
 
-    /// Returns the next low-level event without blocking.
+    /// Returns the next low-level event without blocking, or [`None`] once
+    /// the input is exhausted or the loop has given up on the counterparty.
     pub async fn next_event<'a>(&'a mut self) -> Option<LlEvent<'a>> {
-        let mut buf_filled_len = 0;
-        let mut buf = self.decoder.fillable();
+        if self.message_pending {
+            self.decoder.clear();
+            self.buf_filled_len = 0;
+            self.message_pending = false;
+        }
 
         loop {
             if !self.is_alive {
                 return None;
             }
 
-            let now = Instant::now();
-            let mut timer_heartbeat = Delay::new(now - self.last_heartbeat + self.heartbeat).fuse();
-            let mut timer_test_request =
-                Delay::new(now - self.last_reset + self.heartbeat_soft_tolerance).fuse();
-            let mut timer_logout =
-                Delay::new(now - self.last_reset + self.heartbeat_hard_tolerance).fuse();
+            let now = self.clock.now();
+            let mut timer_heartbeat = self
+                .clock
+                .sleep(
+                    self.heartbeat
+                        .saturating_sub(now.duration_since(self.last_heartbeat)),
+                )
+                .fuse();
+            let mut timer_test_request = if self.test_request_sent {
+                Fuse::terminated()
+            } else {
+                self.clock
+                    .sleep(
+                        self.heartbeat_soft_tolerance
+                            .saturating_sub(now.duration_since(self.last_reset)),
+                    )
+                    .fuse()
+            };
+            let mut timer_logout = self
+                .clock
+                .sleep(
+                    self.heartbeat_hard_tolerance
+                        .saturating_sub(now.duration_since(self.last_reset)),
+                )
+                .fuse();
+
+            let num_bytes_required = self.decoder.num_bytes_required();
+            let buffer = self.decoder.buffer();
+            if buffer.len() < num_bytes_required {
+                buffer.resize(num_bytes_required, 0);
+            }
+            let buf = &mut buffer.as_mut_slice()[self.buf_filled_len..num_bytes_required];
             let mut read_result = self.input.read(buf).fuse();
 
             select! {
                 read_result = read_result => {
                     match read_result {
                         Err(e) => {
+                            self.is_alive = false;
                             return Some(LlEvent::IoError(e));
                         }
+                        Ok(0) => {
+                            // The counterparty closed the connection.
+                            self.is_alive = false;
+                            return None;
+                        }
                         Ok(num_bytes) => {
-                            buf_filled_len += num_bytes;
-                            if buf_filled_len < buf.len() {
+                            self.buf_filled_len += num_bytes;
+                            if self.buf_filled_len < num_bytes_required {
                                 continue;
                             }
 
-                            let result = self.decoder.try_parse();
-                            buf_filled_len = 0;
-                            buf = &mut self.decoder.fillable()[buf_filled_len..];
-
-                            match result {
+                            match self.decoder.try_parse() {
                                 Ok(Some(())) => {
+                                    self.message_pending = true;
                                     let msg = self.decoder.message();
                                     return Some(LlEvent::Message(msg));
                                 }
@@ -111,10 +186,11 @@ where
                     };
                 },
                 () = timer_heartbeat => {
-                    self.last_heartbeat = Instant::now();
+                    self.last_heartbeat = self.clock.now();
                     return Some(LlEvent::Heartbeat);
                 },
                 () = timer_test_request => {
+                    self.test_request_sent = true;
                     return Some(LlEvent::TestRequest);
                 },
                 () = timer_logout => {
@@ -127,7 +203,14 @@ where
 
     /// Resets the FIX counterparty's `Heartbeat <0>` -associated timers.
     pub fn ping_heartbeat(&mut self) {
-        self.last_reset = Instant::now();
+        self.last_reset = self.clock.now();
+        self.test_request_sent = false;
+    }
+
+    /// Restarts the interval until the next [`LlEvent::Heartbeat`], e.g.
+    /// because some other message was just sent to the counterparty.
+    pub fn ping_outbound(&mut self) {
+        self.last_heartbeat = self.clock.now();
     }
 }
 
@@ -140,7 +223,9 @@ pub enum LlEvent<'a> {
     BadMessage(DecodeError),
     /// I/O error at the transport layer.
     IoError(io::Error),
-    /// Time to send a new `HeartBeat <0>` message.
+    /// Time to send a new `HeartBeat <0>` message: nothing was sent for a
+    /// whole heartbeat interval, as reported with
+    /// [`LlEventLoop::ping_outbound`].
     Heartbeat,
     /// The FIX counterparty has missed the `Heartbeat <0>` deadline by some
     /// amount of time, and it's time to send a `Test Request <1>`
@@ -155,6 +240,7 @@ pub enum LlEvent<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::session::MockClock;
     use crate::tagvalue::Decoder;
     use smallvec::smallvec;
     use tokio::io::AsyncWriteExt;
@@ -199,4 +285,29 @@ mod test {
                 || matches!(event, Some(LlEvent::TestRequest))
         );
     }
+
+    #[tokio::test]
+    async fn timers_follow_the_clock() {
+        let clock = MockClock::default();
+        let (input, _peer) = tokio::io::duplex(1024);
+        let mut event_loop = LlEventLoop::new(
+            Decoder::new(crate::Dictionary::fix44().unwrap()).streaming(smallvec![]),
+            input.compat(),
+            Duration::from_secs(30),
+        );
+        event_loop.set_clock(Arc::new(clock.clone()));
+
+        clock.advance(Duration::from_secs(30));
+        let event = event_loop.next_event().await;
+        assert!(matches!(event, Some(LlEvent::Heartbeat)));
+        clock.advance(Duration::from_secs(30));
+        event_loop.ping_outbound();
+        let event = event_loop.next_event().await;
+        assert!(matches!(event, Some(LlEvent::TestRequest)));
+        clock.advance(Duration::from_secs(30));
+        event_loop.ping_outbound();
+        let event = event_loop.next_event().await;
+        assert!(matches!(event, Some(LlEvent::Logout)));
+        assert!(event_loop.next_event().await.is_none());
+    }
 }
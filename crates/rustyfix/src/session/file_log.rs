@@ -0,0 +1,500 @@
+use super::{
+    DisconnectEvent, HeartbeatTimeoutEvent, LogonEvent, LogoutEvent, RejectEvent,
+    ResendCompleteEvent, SequenceGapEvent, SessionId, SessionListener, TapSink, TappedMessage,
+    ThrottleRejectEvent,
+};
+use crate::field_types::Timestamp;
+use crate::tagvalue::{DecodeError, Decoder, Message};
+use rustc_hash::FxHashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Separates the timestamp from the rest of a log line.
+const SEPARATOR: &[u8] = b" : ";
+
+/// Per-session message and event logs in the format of QuickFIX's `FileLog`,
+/// so that logs of both engines can be diffed.
+///
+/// Messages, inbound and outbound alike, go to
+/// `<BeginString>-<SenderCompID>-<TargetCompID>.messages.log` and events to
+/// `<BeginString>-<SenderCompID>-<TargetCompID>.event.log`. Every line starts
+/// with a UTC timestamp with millisecond precision:
+///
+/// ```text
+/// 20240102-14:00:00.123 : 8=FIX.4.4|9=65|35=A|...
+/// ```
+///
+/// [`FileLog`] is a [`SessionListener`], so installing it with
+/// [`FixConnection::set_listener`](super::FixConnection::set_listener) fills
+/// the event log; messages can be logged through a [`FileLogSink`]. Use
+/// [`FileLogReader`] to read them back.
+#[derive(Debug)]
+pub struct FileLog {
+    messages: File,
+    events: File,
+    messages_path: PathBuf,
+    events_path: PathBuf,
+}
+
+impl FileLog {
+    /// Opens (or creates) the logs of `session_id` in `dir` for appending.
+    pub fn open(dir: impl AsRef<Path>, session_id: &SessionId) -> io::Result<Self> {
+        let prefix = Self::file_prefix(session_id);
+        let messages_path = dir.as_ref().join(format!("{prefix}.messages.log"));
+        let events_path = dir.as_ref().join(format!("{prefix}.event.log"));
+        let open = |path: &Path| OpenOptions::new().create(true).append(true).open(path);
+        Ok(Self {
+            messages: open(&messages_path)?,
+            events: open(&events_path)?,
+            messages_path,
+            events_path,
+        })
+    }
+
+    /// Returns the file name prefix QuickFIX uses for `session_id`, e.g.
+    /// `FIX.4.4-SENDER-TARGET`.
+    pub fn file_prefix(session_id: &SessionId) -> String {
+        let mut prefix = format!(
+            "{}-{}",
+            session_id.begin_string(),
+            session_id.sender_comp_id()
+        );
+        if let Some(sub_id) = session_id.sender_sub_id() {
+            prefix.push('_');
+            prefix.push_str(sub_id);
+        }
+        prefix.push('-');
+        prefix.push_str(session_id.target_comp_id());
+        if let Some(sub_id) = session_id.target_sub_id() {
+            prefix.push('_');
+            prefix.push_str(sub_id);
+        }
+        prefix
+    }
+
+    /// Returns the location of the message log.
+    pub fn messages_path(&self) -> &Path {
+        &self.messages_path
+    }
+
+    /// Returns the location of the event log.
+    pub fn events_path(&self) -> &Path {
+        &self.events_path
+    }
+
+    /// Logs a message received from the counterparty.
+    pub fn on_incoming(&mut self, message: &[u8]) -> io::Result<()> {
+        self.log_message(SystemTime::now(), message)
+    }
+
+    /// Logs a message sent to the counterparty.
+    pub fn on_outgoing(&mut self, message: &[u8]) -> io::Result<()> {
+        self.log_message(SystemTime::now(), message)
+    }
+
+    /// Logs a session event, e.g. `Initiated logon request`.
+    pub fn on_event(&mut self, text: &str) -> io::Result<()> {
+        write_line(&mut self.events, SystemTime::now(), text.as_bytes())
+    }
+
+    /// Like QuickFIX, both directions share the message log.
+    fn log_message(&mut self, time: SystemTime, message: &[u8]) -> io::Result<()> {
+        write_line(&mut self.messages, time, message)
+    }
+
+    fn log_event(&mut self, text: &str) {
+        if let Err(err) = self.on_event(text) {
+            log::error!("Failed to write to {}: {err}", self.events_path.display());
+        }
+    }
+}
+
+impl SessionListener for FileLog {
+    fn on_logon(&mut self, logon: &LogonEvent) {
+        let reset = if logon.reset_seq_num_flag {
+            ", sequence numbers reset"
+        } else {
+            ""
+        };
+        self.log_event(&format!(
+            "Logon completed with HeartBtInt = {}{reset}",
+            logon.heartbeat.as_secs()
+        ));
+    }
+
+    fn on_logout(&mut self, logout: &LogoutEvent) {
+        let mut text = if logout.by_counterparty {
+            "Received logout request".to_string()
+        } else {
+            "Initiated logout request".to_string()
+        };
+        if let Some(reason) = &logout.text {
+            text.push_str(": ");
+            text.push_str(reason);
+        }
+        self.log_event(&text);
+    }
+
+    fn on_disconnect(&mut self, disconnect: &DisconnectEvent) {
+        self.log_event(&format!(
+            "Disconnected while {:?}",
+            disconnect.previous_state
+        ));
+    }
+
+    fn on_reject_sent(&mut self, reject: &RejectEvent) {
+        let text = reject.text.as_deref().unwrap_or_default();
+        self.log_event(&format!("Message {} Rejected: {text}", reject.ref_seq_num));
+    }
+
+    fn on_reject_received(&mut self, reject: &RejectEvent) {
+        let text = reject.text.as_deref().unwrap_or_default();
+        self.log_event(&format!(
+            "Message {} Rejected by counterparty: {text}",
+            reject.ref_seq_num
+        ));
+    }
+
+    fn on_sequence_gap(&mut self, gap: &SequenceGapEvent) {
+        self.log_event(&format!(
+            "MsgSeqNum too high, expecting {} but received {}",
+            gap.expected, gap.received
+        ));
+    }
+
+    fn on_resend_complete(&mut self, resend: &ResendCompleteEvent) {
+        self.log_event(&format!(
+            "ResendRequest for messages up to {} has been satisfied",
+            resend.last_seq_num
+        ));
+    }
+
+    fn on_heartbeat_timeout(&mut self, _timeout: &HeartbeatTimeoutEvent) {
+        self.log_event("Timed out waiting for heartbeat");
+    }
+
+    fn on_throttle_reject(&mut self, rejected: &ThrottleRejectEvent) {
+        self.log_event(&format!(
+            "Throttle dropped a message of type {}",
+            String::from_utf8_lossy(&rejected.msg_type)
+        ));
+    }
+}
+
+/// Writes the whole line at once, so concurrent readers never see half of it.
+fn write_line(file: &mut File, time: SystemTime, contents: &[u8]) -> io::Result<()> {
+    let timestamp = chrono::DateTime::<chrono::Utc>::from(time).format("%Y%m%d-%H:%M:%S%.3f");
+    let mut line = timestamp.to_string().into_bytes();
+    line.extend_from_slice(SEPARATOR);
+    line.extend_from_slice(contents);
+    line.push(b'\n');
+    file.write_all(&line)
+}
+
+/// A [`TapSink`] that writes the messages of every tapped session to its
+/// [`FileLog`] in a directory, with the timestamp of the tap.
+#[derive(Debug)]
+pub struct FileLogSink {
+    dir: PathBuf,
+    logs: FxHashMap<SessionId, FileLog>,
+}
+
+impl FileLogSink {
+    /// Creates a [`FileLogSink`] writing to `dir`. Logs are opened on the
+    /// first message of each session.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            logs: FxHashMap::default(),
+        }
+    }
+}
+
+impl TapSink for FileLogSink {
+    fn on_message(&mut self, message: &TappedMessage) {
+        let log = match self.logs.get_mut(&*message.session_id) {
+            Some(log) => log,
+            None => match FileLog::open(&self.dir, &message.session_id) {
+                Ok(log) => self
+                    .logs
+                    .entry(SessionId::clone(&message.session_id))
+                    .or_insert(log),
+                Err(err) => {
+                    log::error!("Failed to open the log of {}: {err}", message.session_id);
+                    return;
+                }
+            },
+        };
+        if let Err(err) = log.log_message(message.timestamp, &message.bytes) {
+            log::error!("Failed to log a message of {}: {err}", message.session_id);
+        }
+    }
+}
+
+/// One line of a [`FileLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// When the line was written.
+    pub timestamp: Timestamp,
+    /// The raw message, or the text of an event.
+    pub contents: Vec<u8>,
+}
+
+impl LogEntry {
+    /// Decodes the contents of a message log entry.
+    pub fn decode<'a>(
+        &'a self,
+        decoder: &'a mut Decoder,
+    ) -> Result<Message<'a, &'a [u8]>, DecodeError> {
+        decoder.decode(self.contents.as_slice())
+    }
+}
+
+/// Reads the [`LogEntry`]s of a message or event log written by [`FileLog`]
+/// or by QuickFIX, in order.
+///
+/// Entries are one per line, except for messages whose data fields contain
+/// line feeds: those span as many lines as their `BodyLength <9>` requires.
+#[derive(Debug)]
+pub struct FileLogReader<R> {
+    reader: R,
+    line: Vec<u8>,
+}
+
+impl FileLogReader<BufReader<File>> {
+    /// Opens the log at `path` for reading.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R> FileLogReader<R>
+where
+    R: BufRead,
+{
+    /// Creates a [`FileLogReader`] over the lines of `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+        }
+    }
+
+    fn parse_line(&self) -> io::Result<LogEntry> {
+        let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Malformed log line: {}", String::from_utf8_lossy(line)),
+            )
+        };
+        let split = line
+            .windows(SEPARATOR.len())
+            .position(|window| window == SEPARATOR)
+            .ok_or_else(invalid)?;
+        let timestamp = Timestamp::parse(&line[..split]).ok_or_else(invalid)?;
+        Ok(LogEntry {
+            timestamp,
+            contents: line[split + SEPARATOR.len()..].to_vec(),
+        })
+    }
+
+    /// Appends lines to the current one for as long as the line feed at its
+    /// end belongs to the message it holds, i.e. to one of its data fields.
+    fn read_message_lines(&mut self) -> io::Result<()> {
+        loop {
+            let Some(split) = self
+                .line
+                .windows(SEPARATOR.len())
+                .position(|window| window == SEPARATOR)
+            else {
+                return Ok(());
+            };
+            let contents = &self.line[split + SEPARATOR.len()..];
+            match message_len(contents) {
+                Some(len) if contents.len() <= len => {
+                    if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+impl<R> Iterator for FileLogReader<R>
+where
+    R: BufRead,
+{
+    type Item = io::Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => return None,
+                Ok(_) if self.line.trim_ascii().is_empty() => continue,
+                Ok(_) => {
+                    if let Err(err) = self.read_message_lines() {
+                        return Some(Err(err));
+                    }
+                    return Some(self.parse_line());
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Returns the length of the FIX message at the start of `contents`
+/// according to its `BodyLength <9>`, or [`None`] if it isn't a message.
+fn message_len(contents: &[u8]) -> Option<usize> {
+    const CHECKSUM_LEN: usize = b"10=000\x01".len();
+    if !contents.starts_with(b"8=") {
+        return None;
+    }
+    let begin_string_end = contents.iter().position(|b| *b == b'\x01')? + 1;
+    let rest = contents[begin_string_end..].strip_prefix(b"9=")?;
+    let digits = rest.iter().position(|b| *b == b'\x01')?;
+    let body_len: usize = std::str::from_utf8(&rest[..digits]).ok()?.parse().ok()?;
+    Some(begin_string_end + 2 + digits + 1 + body_len + CHECKSUM_LEN)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Dictionary;
+    use crate::session::backends::Direction;
+    use std::sync::Arc;
+
+    const LOGON: &[u8] =
+        b"8=FIX.4.4\x019=63\x0135=A\x0149=SENDER\x0156=TARGET\x0134=1\x0152=20240102-14:00:00\x0198=0\x01108=30\x0110=169\x01";
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn file_prefix_includes_sub_ids() {
+        let id = SessionId::new("FIX.4.4", "SENDER", "TARGET");
+        assert_eq!(FileLog::file_prefix(&id), "FIX.4.4-SENDER-TARGET");
+        let id = id.with_sender_sub_id("DESK").with_target_sub_id("EXEC");
+        assert_eq!(FileLog::file_prefix(&id), "FIX.4.4-SENDER_DESK-TARGET_EXEC");
+    }
+
+    #[test]
+    fn messages_and_events_round_trip() {
+        let dir = log_dir("test_file_log");
+        let id = SessionId::new("FIX.4.4", "SENDER", "TARGET");
+        let mut log = FileLog::open(&dir, &id).unwrap();
+        log.on_outgoing(LOGON).unwrap();
+        log.on_event("Initiated logon request").unwrap();
+        assert_eq!(
+            log.messages_path(),
+            dir.join("FIX.4.4-SENDER-TARGET.messages.log")
+        );
+
+        let contents = std::fs::read(log.messages_path()).unwrap();
+        assert_eq!(&contents[21..24], b" : ");
+        let entries: Vec<_> = FileLogReader::open(log.messages_path())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let mut decoder = Decoder::new(Dictionary::fix44().unwrap());
+        let message = entries[0].decode(&mut decoder).unwrap();
+        assert_eq!(message.get_raw(35), Some(&b"A"[..]));
+
+        let events: Vec<_> = FileLogReader::open(log.events_path())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(events[0].contents, b"Initiated logon request");
+    }
+
+    #[test]
+    fn reads_quickfix_logs() {
+        let log = b"20240102-14:00:00.123 : 8=FIX.4.4\x0135=0\x01\n\n20240102-14:00:01.456 : Logon contains ResetSeqNumFlag=Y\n";
+        let entries: Vec<_> = FileLogReader::new(&log[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].contents, b"8=FIX.4.4\x0135=0\x01");
+        assert_eq!(
+            Some(entries[1].timestamp.clone()),
+            Timestamp::parse(b"20240102-14:00:01.456")
+        );
+        assert!(
+            FileLogReader::new(&b"garbage\n"[..])
+                .next()
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn reads_messages_with_line_feeds_in_data_fields() {
+        let body = b"35=B\x0195=3\x0196=a\nb\x01";
+        let mut message = format!("8=FIX.4.4\x019={}\x01", body.len()).into_bytes();
+        message.extend_from_slice(body);
+        message.extend_from_slice(b"10=000\x01");
+        let mut log = b"20240102-14:00:00.123 : ".to_vec();
+        log.extend_from_slice(&message);
+        log.extend_from_slice(b"\n20240102-14:00:01.456 : Disconnected\n");
+
+        let entries: Vec<_> = FileLogReader::new(&log[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].contents, message);
+        assert_eq!(entries[1].contents, b"Disconnected");
+    }
+
+    #[test]
+    fn listener_writes_session_events() {
+        let dir = log_dir("test_file_log_listener");
+        let id = SessionId::new("FIX.4.4", "SENDER", "TARGET");
+        let mut log = FileLog::open(&dir, &id).unwrap();
+        log.on_sequence_gap(&SequenceGapEvent {
+            expected: 2,
+            received: 5,
+        });
+        log.on_heartbeat_timeout(&HeartbeatTimeoutEvent {
+            since_last_received: None,
+            test_req_id: None,
+        });
+
+        let events: Vec<_> = FileLogReader::open(log.events_path())
+            .unwrap()
+            .map(|entry| entry.unwrap().contents)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                &b"MsgSeqNum too high, expecting 2 but received 5"[..],
+                b"Timed out waiting for heartbeat",
+            ]
+        );
+    }
+
+    #[test]
+    fn sink_writes_tapped_messages() {
+        let dir = log_dir("test_file_log_sink");
+        let mut sink = FileLogSink::new(&dir);
+        sink.on_message(&TappedMessage {
+            direction: Direction::Inbound,
+            session_id: Arc::new(SessionId::new("FIX.4.4", "SENDER", "TARGET")),
+            timestamp: SystemTime::UNIX_EPOCH,
+            bytes: LOGON.to_vec(),
+        });
+        let contents = std::fs::read(dir.join("FIX.4.4-SENDER-TARGET.messages.log")).unwrap();
+        assert!(contents.starts_with(b"19700101-00:00:00.000 : 8=FIX.4.4\x01"));
+    }
+}
@@ -0,0 +1,340 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// When and how often an [`Initiator`] reconnects after losing its transport.
+///
+/// The delay before the `n`-th consecutive attempt (starting from zero) is
+/// `initial_delay * multiplier^n`, capped at `max_delay`, and then randomly
+/// shortened or lengthened by up to `jitter` (a fraction between 0 and 1) to
+/// avoid reconnect storms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnect attempt. One second by default.
+    pub initial_delay: Duration,
+    /// Upper bound for the delay between attempts. One minute by default.
+    pub max_delay: Duration,
+    /// Growth factor of the delay after every failed attempt. 2 by default.
+    pub multiplier: f64,
+    /// Random spread applied to every delay, as a fraction of it. 0.1 by
+    /// default.
+    pub jitter: f64,
+    /// Gives up after this many consecutive failed attempts. [`None`], i.e.
+    /// retry forever, by default.
+    pub max_attempts: Option<u32>,
+    /// How often to check whether the session window opened while outside of
+    /// [`Configure::is_session_time`](super::Configure::is_session_time), or
+    /// whether a disabled session was enabled again. One second by default.
+    pub schedule_poll_interval: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.1,
+            max_attempts: None,
+            schedule_poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Returns the delay before the `attempt`-th consecutive reconnect attempt,
+    /// without jitter.
+    pub fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1.0)
+            .powi(attempt.min(i32::MAX as u32) as i32);
+        let delay = self.initial_delay.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+    }
+
+    /// Returns the delay before the `attempt`-th consecutive reconnect attempt,
+    /// jitter included.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.base_delay(attempt).as_secs_f64();
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let spread = rand::rng().random_range(-1.0..=1.0);
+        Duration::from_secs_f64(base * (1.0 + jitter * spread))
+    }
+
+    /// Returns `true` if another attempt is allowed after `failed_attempts`
+    /// consecutive failures.
+    pub fn allows_attempt(&self, failed_attempts: u32) -> bool {
+        self.max_attempts
+            .is_none_or(|max_attempts| failed_attempts < max_attempts)
+    }
+}
+
+#[cfg(feature = "utils-tokio")]
+pub use tokio_initiator::Initiator;
+
+#[cfg(feature = "utils-tokio")]
+mod tokio_initiator {
+    use super::ReconnectPolicy;
+    use crate::session::{Backend, Configure, Session, SessionError, Verify};
+    use std::io;
+
+    /// Keeps an initiator [`Session`] connected over TCP.
+    ///
+    /// Hosts are tried in order, moving on to the next one after each failed
+    /// attempt, with delays according to the [`ReconnectPolicy`]. The
+    /// [`Session`] and its [`SessionHandle`](crate::session::SessionHandle)
+    /// survive reconnects: sequence numbers are kept (or restored from the
+    /// [`Backend`]) and messages sent while disconnected go out after the
+    /// next Logon.
+    #[derive(Debug)]
+    #[cfg_attr(docsrs, doc(cfg(feature = "utils-tokio")))]
+    pub struct Initiator<B, C, V> {
+        session: Session<B, C, V>,
+        hosts: Vec<String>,
+        policy: ReconnectPolicy,
+    }
+
+    impl<B, C, V> Initiator<B, C, V>
+    where
+        B: Backend,
+        C: Configure,
+        V: Verify,
+    {
+        /// Creates a new [`Initiator`]. `hosts` are `host:port` addresses in
+        /// order of preference; `session` should use
+        /// [`SessionRole::Initiator`](crate::session::SessionRole::Initiator).
+        pub fn new(
+            session: Session<B, C, V>,
+            hosts: impl IntoIterator<Item = impl Into<String>>,
+            policy: ReconnectPolicy,
+        ) -> Self {
+            Self {
+                session,
+                hosts: hosts.into_iter().map(Into::into).collect(),
+                policy,
+            }
+        }
+
+        /// Returns an immutable reference to the [`Session`].
+        pub fn session(&self) -> &Session<B, C, V> {
+            &self.session
+        }
+
+        /// Returns the [`ReconnectPolicy`] in use.
+        pub fn policy(&self) -> &ReconnectPolicy {
+            &self.policy
+        }
+
+        /// Connects and keeps reconnecting until the session is logged out
+        /// gracefully, which returns `Ok(())`, or until
+        /// [`ReconnectPolicy::max_attempts`] consecutive attempts fail, which
+        /// returns the last error. A Logout at the end of a
+        /// [`SessionSchedule`](crate::session::SessionSchedule) window
+        /// doesn't count: the session reconnects when the next window opens.
+        /// Neither do Logouts to reset sequence numbers or to disable the
+        /// session, see [`SessionControl`](crate::session::SessionControl).
+        pub async fn run(&mut self) -> Result<(), SessionError> {
+            if self.hosts.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "No hosts").into());
+            }
+            let mut failed_attempts = 0;
+            let mut next_host = 0;
+            loop {
+                while !self.is_session_time() || !self.session.is_enabled() {
+                    tokio::time::sleep(self.policy.schedule_poll_interval).await;
+                }
+
+                let host = &self.hosts[next_host];
+                next_host = (next_host + 1) % self.hosts.len();
+                log::info!("Connecting to {host}");
+                let error = match tokio::net::TcpStream::connect(host.as_str()).await {
+                    Ok(stream) => match self.session.run_tcp(stream).await {
+                        Ok(())
+                            if self.is_session_time()
+                                && self.session.is_enabled()
+                                && !self.session.was_reset() =>
+                        {
+                            return Ok(());
+                        }
+                        Ok(()) => {
+                            // Logged out at the end of the session window, to
+                            // reset sequence numbers or to stay disabled.
+                            failed_attempts = 0;
+                            next_host = (next_host + self.hosts.len() - 1) % self.hosts.len();
+                            continue;
+                        }
+                        Err(err) => {
+                            if self.session.was_established() {
+                                failed_attempts = 0;
+                                // Reconnect to the host that was working.
+                                next_host = (next_host + self.hosts.len() - 1) % self.hosts.len();
+                            }
+                            err
+                        }
+                    },
+                    Err(err) => err.into(),
+                };
+                log::warn!("Session to {host} failed: {error}");
+
+                if !self.policy.allows_attempt(failed_attempts + 1) {
+                    return Err(error);
+                }
+                let delay = self.policy.delay(failed_attempts);
+                failed_attempts += 1;
+                log::info!("Reconnecting in {delay:?}");
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        fn is_session_time(&self) -> bool {
+            let connection = self.session.connection();
+            connection
+                .config()
+                .is_session_time(connection.clock().system_time())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn base_delay_grows_exponentially_up_to_max() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            ..Default::default()
+        };
+        assert_eq!(policy.base_delay(0), Duration::from_millis(100));
+        assert_eq!(policy.base_delay(1), Duration::from_millis(200));
+        assert_eq!(policy.base_delay(3), Duration::from_millis(800));
+        assert_eq!(policy.base_delay(4), Duration::from_secs(1));
+        assert_eq!(policy.base_delay(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn delay_stays_within_jitter() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(10),
+            jitter: 0.5,
+            ..Default::default()
+        };
+        let delays: Vec<_> = (0..100).map(|_| policy.delay(0)).collect();
+        for delay in &delays {
+            assert!(*delay >= Duration::from_secs(5) && *delay <= Duration::from_secs(15));
+        }
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+        let no_jitter = ReconnectPolicy {
+            jitter: 0.0,
+            ..policy
+        };
+        assert_eq!(no_jitter.delay(0), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn max_attempts() {
+        let policy = ReconnectPolicy {
+            max_attempts: Some(2),
+            ..Default::default()
+        };
+        assert!(policy.allows_attempt(0));
+        assert!(policy.allows_attempt(1));
+        assert!(!policy.allows_attempt(2));
+        assert!(ReconnectPolicy::default().allows_attempt(u32::MAX));
+    }
+
+    #[cfg(feature = "utils-tokio")]
+    mod tokio_initiator {
+        use super::*;
+        use crate::Dictionary;
+        use crate::session::backends::MemoryBackend;
+        use crate::session::{
+            Config, FixConnection, NoOpVerifier, Session, SessionError, SessionHandle, SessionRole,
+        };
+        use tokio::net::TcpListener;
+
+        fn session(
+            sender: &str,
+            target: &str,
+            role: SessionRole,
+        ) -> (Session<MemoryBackend, Config, NoOpVerifier>, SessionHandle) {
+            let connection = FixConnection::new(
+                MemoryBackend::new(sender, target),
+                Config::default(),
+                NoOpVerifier,
+            );
+            Session::new(connection, role, Dictionary::fix44().unwrap())
+        }
+
+        fn fast_policy(max_attempts: Option<u32>) -> ReconnectPolicy {
+            ReconnectPolicy {
+                initial_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(50),
+                max_attempts,
+                ..Default::default()
+            }
+        }
+
+        /// Returns an address where nobody is listening.
+        async fn dead_host() -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().to_string()
+        }
+
+        #[tokio::test]
+        async fn fails_over_and_keeps_sequence_numbers() {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let live_host = listener.local_addr().unwrap().to_string();
+            let (client, client_handle) = session("CLIENT", "SERVER", SessionRole::Initiator);
+            let mut initiator =
+                Initiator::new(client, [dead_host().await, live_host], fast_policy(None));
+
+            // Queued until the second connection completes the Logon.
+            client_handle
+                .send(
+                    b"D",
+                    b"11=ORDER\x0154=1\x0160=20240102-14:00:00\x0140=1\x01",
+                )
+                .unwrap();
+            let server = async {
+                // The first connection is dropped right after the Logon.
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = stream;
+                let mut buffer = [0; 4096];
+                tokio::io::AsyncReadExt::read(&mut stream, &mut buffer)
+                    .await
+                    .unwrap();
+                drop(stream);
+
+                // The initiator doesn't start over from MsgSeqNum <34> = 1,
+                // otherwise this acceptor would log it out.
+                let (mut acceptor, mut handle) = session("SERVER", "CLIENT", SessionRole::Acceptor);
+                acceptor
+                    .connection_mut()
+                    .set_seq_numbers(crate::session::SeqNumbers {
+                        next_inbound: 2,
+                        next_outbound: 1,
+                    });
+                let (stream, _) = listener.accept().await.unwrap();
+                let app = async {
+                    assert!(handle.recv().await.is_some());
+                    client_handle.logout("").unwrap();
+                    handle
+                };
+                let (result, _handle) = tokio::join!(acceptor.run_tcp(stream), app);
+                result.unwrap();
+            };
+            let (result, ()) = tokio::join!(initiator.run(), server);
+            result.unwrap();
+            assert!(initiator.session().was_established());
+        }
+
+        #[tokio::test]
+        async fn gives_up_after_max_attempts() {
+            let (client, _client_handle) = session("CLIENT", "SERVER", SessionRole::Initiator);
+            let mut initiator = Initiator::new(client, [dead_host().await], fast_policy(Some(3)));
+            assert!(matches!(initiator.run().await, Err(SessionError::Io(_))));
+        }
+    }
+}
@@ -0,0 +1,854 @@
+//! Crash-safe, append-only storage for FIX session state.
+//!
+//! A [`Journal`] is a single file of length-prefixed, checksummed message
+//! records. Records are only ever appended, so a crash can at worst leave a
+//! partially written record at the very end of the file; [`Journal::open`]
+//! detects such torn writes and truncates them away. Corruption anywhere else
+//! is reported as an error rather than discarded. Sequence numbers live in
+//! a separate, much smaller [`SeqNumFile`] that is replaced atomically on every
+//! update, in the same spirit as QuickFIX's `.seqnums` files, and the time
+//! the store was created in a [`SessionFile`], like QuickFIX's `.session`
+//! files.
+//!
+//! # Record layout
+//!
+//! The file starts with the 8-byte magic `RFXJRNL1`, followed by records:
+//!
+//! | Size (bytes) | Content                                       |
+//! |--------------|-----------------------------------------------|
+//! | 4            | Payload length, little endian                 |
+//! | 8            | `MsgSeqNum <34>`, little endian               |
+//! | 1            | Direction: `0` for inbound, `1` for outbound  |
+//! | 4            | CRC-32 of the three fields above plus payload |
+//! | *length*     | The raw FIX message                           |
+
+use super::SeqNumbers;
+use super::backends::{BackendError, Direction};
+use crate::FieldType;
+use crate::field_types::Timestamp;
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 8] = b"RFXJRNL1";
+const RECORD_HEADER_LEN: usize = 4 + 8 + 1 + 4;
+/// Upper bound on the payload of a single record. Anything larger is treated
+/// as corruption rather than an allocation request.
+const MAX_PAYLOAD_LEN: usize = 64 * 1024 * 1024;
+
+/// When a [`Journal`] asks the operating system to flush written data to
+/// stable storage.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// `fsync` after every single message. Slowest, but nothing that was
+    /// acknowledged by [`Journal::append`] can be lost.
+    #[default]
+    EveryMessage,
+    /// `fsync` once `max_messages` records have accumulated or `max_delay` has
+    /// elapsed since the last sync, whichever comes first. Both conditions are
+    /// checked on append and on [`Journal::sync_if_due`].
+    Batched {
+        /// Maximum number of unsynced records.
+        max_messages: usize,
+        /// Maximum time a record may stay unsynced.
+        max_delay: Duration,
+    },
+    /// Never `fsync` explicitly and leave it to the operating system. A
+    /// process crash loses nothing, but a power loss or kernel panic might.
+    Never,
+}
+
+/// A single message stored in a [`Journal`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalRecord {
+    /// The `MsgSeqNum <34>` of the message.
+    pub seq_num: u64,
+    /// Whether the message was received or sent.
+    pub direction: Direction,
+    /// The raw FIX message.
+    pub message: Vec<u8>,
+}
+
+/// An append-only, checksummed message log. See the [module
+/// documentation](self) for the on-disk format.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    fsync_policy: FsyncPolicy,
+    unsynced: usize,
+    last_sync: Instant,
+    appended_since_compaction: u64,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, creating it if necessary, and returns all
+    /// intact records in the order they were written.
+    ///
+    /// If the file ends with a torn record, i.e. one the process died halfway
+    /// through writing, the file is truncated right before it. Files in the
+    /// plain-text `seq:message` format written by older versions of
+    /// [`FileBackend`](super::backends::FileBackend) are converted in place.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`BackendError::Serialization`] if any record other than
+    /// the last one is corrupted, leaving the file untouched.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        fsync_policy: FsyncPolicy,
+    ) -> Result<(Self, Vec<JournalRecord>), BackendError> {
+        let path = path.into();
+        let mut contents = Vec::new();
+        if path.exists() {
+            File::open(&path)?.read_to_end(&mut contents)?;
+        }
+
+        let records = if contents.is_empty() {
+            write_atomically(&path, MAGIC, fsync_policy != FsyncPolicy::Never)?;
+            Vec::new()
+        } else if contents.starts_with(MAGIC) {
+            let (records, valid_len) = decode_records(&contents[MAGIC.len()..])
+                .map_err(|offset| corrupted(&path, MAGIC.len() + offset))?;
+            let valid_len = (MAGIC.len() + valid_len) as u64;
+            if valid_len < contents.len() as u64 {
+                log::warn!(
+                    "Journal {}: discarding {} bytes of a torn record at offset {}",
+                    path.display(),
+                    contents.len() as u64 - valid_len,
+                    valid_len
+                );
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(valid_len)?;
+                file.sync_all()?;
+            }
+            records
+        } else {
+            log::info!(
+                "Journal {}: converting legacy plain-text message store",
+                path.display()
+            );
+            let records = decode_legacy(&contents);
+            write_atomically(&path, &encode_file(&records), true)?;
+            records
+        };
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+        let journal = Self {
+            path,
+            file,
+            fsync_policy,
+            unsynced: 0,
+            last_sync: Instant::now(),
+            appended_since_compaction: records.len() as u64,
+        };
+        Ok((journal, records))
+    }
+
+    /// Returns the location of `self` on disk.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the [`FsyncPolicy`] in use.
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.fsync_policy
+    }
+
+    /// Returns the number of records appended since the journal was last
+    /// opened or compacted.
+    pub fn appended_since_compaction(&self) -> u64 {
+        self.appended_since_compaction
+    }
+
+    /// Appends a message to the journal, syncing according to the
+    /// [`FsyncPolicy`].
+    pub fn append(
+        &mut self,
+        seq_num: u64,
+        direction: Direction,
+        message: &[u8],
+    ) -> Result<(), BackendError> {
+        if message.len() > MAX_PAYLOAD_LEN {
+            return Err(BackendError::CapacityExceeded);
+        }
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + message.len());
+        encode_record(&mut record, seq_num, direction, message);
+        // A single write keeps the window for torn records as small as the
+        // operating system allows.
+        self.file.write_all(&record)?;
+        self.unsynced += 1;
+        self.appended_since_compaction += 1;
+        self.sync_if_due()
+    }
+
+    /// Syncs pending records if the [`FsyncPolicy`] says so. Useful to bound
+    /// the age of a [`FsyncPolicy::Batched`] batch on idle sessions.
+    pub fn sync_if_due(&mut self) -> Result<(), BackendError> {
+        let due = match self.fsync_policy {
+            FsyncPolicy::EveryMessage => self.unsynced > 0,
+            FsyncPolicy::Batched {
+                max_messages,
+                max_delay,
+            } => {
+                self.unsynced > 0
+                    && (self.unsynced >= max_messages || self.last_sync.elapsed() >= max_delay)
+            }
+            FsyncPolicy::Never => false,
+        };
+        if due { self.sync() } else { Ok(()) }
+    }
+
+    /// Flushes all pending records to stable storage, regardless of the
+    /// [`FsyncPolicy`].
+    pub fn sync(&mut self) -> Result<(), BackendError> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Reads back all records currently in the journal.
+    pub fn records(&self) -> Result<Vec<JournalRecord>, BackendError> {
+        let mut records = Vec::new();
+        self.scan(|record| records.push(record))?;
+        Ok(records)
+    }
+
+    /// Returns the most recent copy of each message with the given `direction`
+    /// and a sequence number within `range`, sorted by sequence number. Only
+    /// those are kept in memory while the journal is read.
+    pub fn messages_in_range(
+        &self,
+        direction: Direction,
+        range: Range<u64>,
+    ) -> Result<Vec<JournalRecord>, BackendError> {
+        let mut selected = Vec::new();
+        self.scan(|record| {
+            if record.direction == direction && range.contains(&record.seq_num) {
+                selected.push(record);
+            }
+        })?;
+        // A stable sort keeps later duplicates after earlier ones...
+        selected.sort_by_key(|r| r.seq_num);
+        // ...so keeping the last element of each run keeps the newest copy.
+        let mut deduplicated: Vec<JournalRecord> = Vec::with_capacity(selected.len());
+        for record in selected {
+            match deduplicated.last_mut() {
+                Some(last) if last.seq_num == record.seq_num => *last = record,
+                _ => deduplicated.push(record),
+            }
+        }
+        Ok(deduplicated)
+    }
+
+    /// Rewrites the journal so that it only contains the newest copy of the
+    /// `retained_per_direction` most recent messages in each direction.
+    ///
+    /// The compacted journal is written to a temporary file and atomically
+    /// renamed over the original, so a crash during compaction leaves either
+    /// the old or the new journal behind, never a mix of the two.
+    pub fn compact(&mut self, retained_per_direction: usize) -> Result<(), BackendError> {
+        self.sync()?;
+        // Sequence numbers first, so that only retained messages are ever
+        // held in memory.
+        let mut seq_nums = [BTreeSet::new(), BTreeSet::new()];
+        self.scan(|record| {
+            seq_nums[usize::from(direction_to_byte(record.direction))].insert(record.seq_num);
+        })?;
+        let mut retained = Vec::new();
+        for direction in [Direction::Inbound, Direction::Outbound] {
+            let seq_nums = &seq_nums[usize::from(direction_to_byte(direction))];
+            let skip = seq_nums.len().saturating_sub(retained_per_direction);
+            let Some(oldest) = seq_nums.iter().nth(skip) else {
+                continue;
+            };
+            retained.extend(self.messages_in_range(direction, *oldest..u64::MAX)?);
+        }
+        let records_before = self.appended_since_compaction;
+        self.rewrite(&retained)?;
+        log::debug!(
+            "Compacted journal {}: {} records retained, {} appended since last compaction",
+            self.path.display(),
+            retained.len(),
+            records_before
+        );
+        Ok(())
+    }
+
+    /// Rewrites the journal, keeping only the records for which `keep`
+    /// returns `true`.
+    pub fn retain<F>(&mut self, mut keep: F) -> Result<(), BackendError>
+    where
+        F: FnMut(&JournalRecord) -> bool,
+    {
+        self.sync()?;
+        let mut records = self.records()?;
+        records.retain(|record| keep(record));
+        self.rewrite(&records)
+    }
+
+    /// Discards all records, e.g. after a sequence number reset.
+    pub fn clear(&mut self) -> Result<(), BackendError> {
+        self.rewrite(&[])
+    }
+
+    /// Calls `f` with every record in the order they were written, reading
+    /// one record at a time. A record cut short by the end of the file is
+    /// ignored, as it's still being written.
+    fn scan<F>(&self, mut f: F) -> Result<(), BackendError>
+    where
+        F: FnMut(JournalRecord),
+    {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut magic = [0; MAGIC.len()];
+        if read_up_to(&mut reader, &mut magic)? < MAGIC.len() {
+            return Ok(());
+        }
+        let mut offset = MAGIC.len();
+        let mut header = [0; RECORD_HEADER_LEN];
+        let mut record = Vec::new();
+        loop {
+            if read_up_to(&mut reader, &mut header)? < RECORD_HEADER_LEN {
+                return Ok(());
+            }
+            let len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+            if len > MAX_PAYLOAD_LEN {
+                return Err(corrupted(&self.path, offset));
+            }
+            record.clear();
+            record.extend_from_slice(&header);
+            if (&mut reader).take(len as u64).read_to_end(&mut record)? < len {
+                return Ok(());
+            }
+            let Some((decoded, len)) = decode_record(&record) else {
+                return Err(corrupted(&self.path, offset));
+            };
+            f(decoded);
+            offset += len;
+        }
+    }
+
+    fn rewrite(&mut self, records: &[JournalRecord]) -> Result<(), BackendError> {
+        write_atomically(&self.path, &encode_file(records), true)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+        self.appended_since_compaction = 0;
+        Ok(())
+    }
+}
+
+/// A QuickFIX-compatible sequence number file, i.e. a single line in the form
+/// `SSSSSSSSSS : TTTTTTTTTT` where `S` is the next outbound (sender) and `T`
+/// the next inbound (target) sequence number.
+///
+/// Every update writes a temporary file and renames it over the previous one,
+/// so readers never observe a half-written file.
+#[derive(Debug, Clone)]
+pub struct SeqNumFile {
+    path: PathBuf,
+}
+
+impl SeqNumFile {
+    /// Creates a handle to the sequence number file at `path`. The file is not
+    /// touched until [`SeqNumFile::load`] or [`SeqNumFile::store`] are called.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the location of `self` on disk.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the stored sequence numbers, if the file exists.
+    pub fn load(&self) -> Result<Option<SeqNumbers>, BackendError> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let parse = |s: &str| s.trim().parse::<u64>().ok().filter(|n| *n > 0);
+        match contents.split_once(':') {
+            Some((outbound, inbound)) => match (parse(outbound), parse(inbound)) {
+                (Some(next_outbound), Some(next_inbound)) => Ok(Some(SeqNumbers {
+                    next_inbound,
+                    next_outbound,
+                })),
+                _ => Err(BackendError::Serialization(format!(
+                    "malformed sequence number file {}",
+                    self.path.display()
+                ))),
+            },
+            None => Err(BackendError::Serialization(format!(
+                "malformed sequence number file {}",
+                self.path.display()
+            ))),
+        }
+    }
+
+    /// Atomically replaces the stored sequence numbers. `fsync` controls
+    /// whether the new contents are flushed to stable storage before the
+    /// rename.
+    pub fn store(&self, seq_numbers: SeqNumbers, fsync: bool) -> Result<(), BackendError> {
+        let line = format!(
+            "{:0>10} : {:0>10}",
+            seq_numbers.next_outbound, seq_numbers.next_inbound
+        );
+        write_atomically(&self.path, line.as_bytes(), fsync)
+    }
+}
+
+/// A QuickFIX-compatible session file, holding the creation time of a
+/// message store as a single `YYYYMMDD-HH:MM:SS` UTC timestamp.
+///
+/// QuickFIX compares this time against the session schedule to tell whether
+/// stored sequence numbers belong to an earlier window.
+#[derive(Debug, Clone)]
+pub struct SessionFile {
+    path: PathBuf,
+}
+
+impl SessionFile {
+    /// Creates a handle to the session file at `path`. The file is not
+    /// touched until [`SessionFile::load`] or [`SessionFile::store`] are
+    /// called.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the location of `self` on disk.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the stored creation time, if the file exists.
+    pub fn load(&self) -> Result<Option<SystemTime>, BackendError> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Timestamp::deserialize(contents.trim_ascii())
+            .ok()
+            .and_then(|timestamp| timestamp.to_system_time())
+            .map(Some)
+            .ok_or_else(|| {
+                BackendError::Serialization(format!(
+                    "malformed session file {}",
+                    self.path.display()
+                ))
+            })
+    }
+
+    /// Atomically replaces the stored creation time with `time`. Returns the
+    /// time as stored, i.e. truncated to whole seconds.
+    pub fn store(&self, time: SystemTime) -> Result<SystemTime, BackendError> {
+        let since_epoch = time.duration_since(UNIX_EPOCH).map_err(|_| {
+            BackendError::Serialization("creation time before the UNIX epoch".to_string())
+        })?;
+        let time = UNIX_EPOCH + Duration::from_secs(since_epoch.as_secs());
+        let timestamp = Timestamp::from_system_time(time)
+            .ok_or_else(|| BackendError::Serialization("creation time out of range".to_string()))?;
+        let mut contents = timestamp.to_bytes();
+        // Drop the milliseconds, as QuickFIX does.
+        contents.truncate(17);
+        write_atomically(&self.path, &contents, true)?;
+        Ok(time)
+    }
+}
+
+/// Writes `contents` to a sibling temporary file and renames it over `path`.
+fn write_atomically(path: &Path, contents: &[u8], fsync: bool) -> Result<(), BackendError> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        if fsync {
+            file.sync_all()?;
+        }
+    }
+    std::fs::rename(&tmp_path, path)?;
+    if fsync {
+        sync_parent_dir(path);
+    }
+    Ok(())
+}
+
+/// Fills `buffer` from `reader` and returns how many bytes were read, which is
+/// less than its length only at the end of the file.
+fn read_up_to(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+/// Makes a rename durable. Directories can't be opened for syncing on every
+/// platform, so failures are ignored.
+fn sync_parent_dir(path: &Path) {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+fn encode_file(records: &[JournalRecord]) -> Vec<u8> {
+    let mut contents = MAGIC.to_vec();
+    for record in records {
+        encode_record(
+            &mut contents,
+            record.seq_num,
+            record.direction,
+            &record.message,
+        );
+    }
+    contents
+}
+
+fn encode_record(buffer: &mut Vec<u8>, seq_num: u64, direction: Direction, message: &[u8]) {
+    let start = buffer.len();
+    buffer.extend_from_slice(&(message.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(&seq_num.to_le_bytes());
+    buffer.push(direction_to_byte(direction));
+    let checksum = crc32(&[&buffer[start..], message]);
+    buffer.extend_from_slice(&checksum.to_le_bytes());
+    buffer.extend_from_slice(message);
+}
+
+/// Decodes all records and returns them alongside the number of bytes they
+/// span. Only a torn record may follow them; corruption anywhere else is
+/// reported as the offset of the first bad record.
+fn decode_records(mut data: &[u8]) -> Result<(Vec<JournalRecord>, usize), usize> {
+    let mut records = Vec::new();
+    let mut valid_len = 0;
+    while !data.is_empty() {
+        let Some((record, len)) = decode_record(data) else {
+            if is_torn(data) {
+                break;
+            }
+            return Err(valid_len);
+        };
+        records.push(record);
+        valid_len += len;
+        data = &data[len..];
+    }
+    Ok((records, valid_len))
+}
+
+/// Decodes the record at the start of `data` and returns it alongside its
+/// length in bytes, or [`None`] if it's incomplete or corrupted.
+fn decode_record(data: &[u8]) -> Option<(JournalRecord, usize)> {
+    if data.len() < RECORD_HEADER_LEN {
+        return None;
+    }
+    let len = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+    let seq_num = u64::from_le_bytes(data[4..12].try_into().unwrap());
+    let direction = direction_from_byte(data[12])?;
+    let checksum = u32::from_le_bytes(data[13..17].try_into().unwrap());
+    if len > MAX_PAYLOAD_LEN || data.len() < RECORD_HEADER_LEN + len {
+        return None;
+    }
+    let message = &data[RECORD_HEADER_LEN..RECORD_HEADER_LEN + len];
+    if crc32(&[&data[..13], message]) != checksum {
+        return None;
+    }
+    let record = JournalRecord {
+        seq_num,
+        direction,
+        message: message.to_vec(),
+    };
+    Some((record, RECORD_HEADER_LEN + len))
+}
+
+/// Returns `true` if the bad record at the start of `data` is the last one,
+/// as left behind by a crash halfway through [`Journal::append`], i.e. no
+/// intact record follows it. Its length isn't covered by a checksum and can't
+/// be trusted, so every later offset is tried. Zeros, which some filesystems
+/// fill unwritten blocks with, never decode as a record.
+fn is_torn(data: &[u8]) -> bool {
+    (1..data.len()).all(|offset| decode_record(&data[offset..]).is_none())
+}
+
+fn corrupted(path: &Path, offset: usize) -> BackendError {
+    BackendError::Serialization(format!(
+        "journal {} is corrupted at offset {offset}",
+        path.display()
+    ))
+}
+
+/// Parses the `seq:message` lines of the old outbound-only store.
+fn decode_legacy(contents: &[u8]) -> Vec<JournalRecord> {
+    String::from_utf8_lossy(contents)
+        .lines()
+        .filter_map(|line| {
+            let (seq_num, message) = line.split_once(':')?;
+            Some(JournalRecord {
+                seq_num: seq_num.parse().ok()?,
+                direction: Direction::Outbound,
+                message: message.as_bytes().to_vec(),
+            })
+        })
+        .collect()
+}
+
+fn direction_to_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Inbound => 0,
+        Direction::Outbound => 1,
+    }
+}
+
+fn direction_from_byte(byte: u8) -> Option<Direction> {
+    match byte {
+        0 => Some(Direction::Inbound),
+        1 => Some(Direction::Outbound),
+        _ => None,
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3) over the concatenation of `chunks`.
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for chunk in chunks {
+        for byte in *chunk {
+            crc = CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn records_survive_reopening() {
+        let path = temp_path("rustyfix_journal_reopen.journal");
+        {
+            let (mut journal, records) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+            assert!(records.is_empty());
+            journal.append(1, Direction::Outbound, b"first").unwrap();
+            journal.append(1, Direction::Inbound, b"second").unwrap();
+        }
+        let (_journal, records) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].message, b"first");
+        assert_eq!(records[1].direction, Direction::Inbound);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn torn_tail_is_truncated() {
+        let path = temp_path("rustyfix_journal_torn.journal");
+        {
+            let (mut journal, _) = Journal::open(&path, FsyncPolicy::EveryMessage).unwrap();
+            journal.append(1, Direction::Outbound, b"intact").unwrap();
+            journal.append(2, Direction::Outbound, b"torn").unwrap();
+        }
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 2).unwrap();
+        drop(file);
+
+        let (mut journal, records) = Journal::open(&path, FsyncPolicy::EveryMessage).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message, b"intact");
+        // New records go right after the last intact one.
+        journal.append(2, Direction::Outbound, b"retry").unwrap();
+        let records = journal.records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].message, b"retry");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn corrupted_record_is_discarded() {
+        let path = temp_path("rustyfix_journal_corrupted.journal");
+        {
+            let (mut journal, _) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+            journal.append(1, Direction::Outbound, b"good").unwrap();
+            journal.append(2, Direction::Outbound, b"bad").unwrap();
+        }
+        let mut contents = std::fs::read(&path).unwrap();
+        let last = contents.len() - 1;
+        contents[last] ^= 0xFF;
+        std::fs::write(&path, contents).unwrap();
+
+        let (_journal, records) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].seq_num, 1);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn corruption_before_the_last_record_is_an_error() {
+        let path = temp_path("rustyfix_journal_corrupted_middle.journal");
+        {
+            let (mut journal, _) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+            journal.append(1, Direction::Outbound, b"good").unwrap();
+            journal.append(2, Direction::Outbound, b"bad").unwrap();
+            journal.append(3, Direction::Outbound, b"good").unwrap();
+        }
+        let mut contents = std::fs::read(&path).unwrap();
+        let bad = MAGIC.len() + RECORD_HEADER_LEN + 4 + RECORD_HEADER_LEN;
+        contents[bad] ^= 0xFF;
+        std::fs::write(&path, &contents).unwrap();
+
+        let err = Journal::open(&path, FsyncPolicy::Never).unwrap_err();
+        assert!(matches!(err, BackendError::Serialization(msg) if msg.ends_with("offset 29")));
+        // Nothing was truncated.
+        assert_eq!(std::fs::read(&path).unwrap(), contents);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn corrupted_length_before_the_last_record_is_an_error() {
+        let path = temp_path("rustyfix_journal_corrupted_length.journal");
+        {
+            let (mut journal, _) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+            journal.append(1, Direction::Outbound, b"good").unwrap();
+            journal.append(2, Direction::Outbound, b"bad").unwrap();
+            journal.append(3, Direction::Outbound, b"good").unwrap();
+        }
+        let mut contents = std::fs::read(&path).unwrap();
+        let bad = MAGIC.len() + RECORD_HEADER_LEN + 4;
+        contents[bad + 3] = 0x7F;
+        std::fs::write(&path, &contents).unwrap();
+
+        let err = Journal::open(&path, FsyncPolicy::Never).unwrap_err();
+        assert!(matches!(err, BackendError::Serialization(msg) if msg.ends_with("offset 29")));
+        assert_eq!(std::fs::read(&path).unwrap(), contents);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn zero_filled_tail_is_truncated() {
+        let path = temp_path("rustyfix_journal_zero_filled.journal");
+        {
+            let (mut journal, _) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+            journal.append(1, Direction::Outbound, b"intact").unwrap();
+        }
+        let mut contents = std::fs::read(&path).unwrap();
+        let intact_len = contents.len() as u64;
+        contents.extend_from_slice(&[0; 64]);
+        std::fs::write(&path, contents).unwrap();
+
+        let (_journal, records) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), intact_len);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn compaction_keeps_newest_copies() {
+        let path = temp_path("rustyfix_journal_compaction.journal");
+        let (mut journal, _) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+        for seq_num in 1..=5 {
+            journal
+                .append(
+                    seq_num,
+                    Direction::Outbound,
+                    format!("v1-{seq_num}").as_bytes(),
+                )
+                .unwrap();
+        }
+        journal.append(5, Direction::Outbound, b"v2-5").unwrap();
+        journal.append(1, Direction::Inbound, b"in-1").unwrap();
+        journal.compact(2).unwrap();
+        assert_eq!(journal.appended_since_compaction(), 0);
+
+        let outbound = journal
+            .messages_in_range(Direction::Outbound, 0..u64::MAX)
+            .unwrap();
+        assert_eq!(outbound.len(), 2);
+        assert_eq!(outbound[0].message, b"v1-4");
+        assert_eq!(outbound[1].message, b"v2-5");
+        let inbound = journal
+            .messages_in_range(Direction::Inbound, 0..u64::MAX)
+            .unwrap();
+        assert_eq!(inbound.len(), 1);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn legacy_store_is_converted() {
+        let path = temp_path("rustyfix_journal_legacy.journal");
+        std::fs::write(&path, "1:8=FIX.4.2\x0134=1\x01\n2:8=FIX.4.2\x0134=2\x01\n").unwrap();
+        let (_journal, records) = Journal::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].seq_num, 2);
+        assert!(std::fs::read(&path).unwrap().starts_with(MAGIC));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn seq_num_file_round_trip() {
+        let path = temp_path("rustyfix_journal_test.seqnums");
+        let file = SeqNumFile::new(&path);
+        assert!(file.load().unwrap().is_none());
+        file.store(
+            SeqNumbers {
+                next_inbound: 7,
+                next_outbound: 42,
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "0000000042 : 0000000007"
+        );
+        let loaded = file.load().unwrap().unwrap();
+        assert_eq!(loaded.next_inbound(), 7);
+        assert_eq!(loaded.next_outbound(), 42);
+        let _ = std::fs::remove_file(path);
+    }
+}
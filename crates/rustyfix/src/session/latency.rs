@@ -0,0 +1,231 @@
+use crate::field_types::Timestamp;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Upper bounds of the [`LatencyHistogram`] buckets, in microseconds. A last,
+/// unbounded bucket holds everything slower.
+const BUCKET_BOUNDS_MICROS: [u64; 16] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// Why [`LatencyChecker::check`] refused a `SendingTime <52>`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LatencyError {
+    /// `SendingTime <52>` is missing or isn't a valid UTC timestamp.
+    #[error("SendingTime <52> is missing or malformed")]
+    InvalidSendingTime,
+    /// The message took longer than allowed to arrive.
+    #[error("SendingTime <52> is {latency:?} in the past")]
+    TooOld {
+        /// How long ago the message was sent.
+        latency: Duration,
+    },
+    /// The counterparty's clock is ahead of ours by more than the allowed
+    /// skew.
+    #[error("SendingTime <52> is {ahead:?} in the future")]
+    InTheFuture {
+        /// How far ahead the counterparty's clock is.
+        ahead: Duration,
+    },
+}
+
+/// Checks `SendingTime <52>` of inbound messages against the local clock and
+/// records the observed latencies in a [`LatencyHistogram`].
+///
+/// A message is accepted if it was sent at most
+/// [`Configure::max_allowed_latency`](super::Configure::max_allowed_latency)
+/// plus [`Configure::max_clock_skew`](super::Configure::max_clock_skew) ago,
+/// and at most the clock skew in the future.
+#[derive(Debug, Clone)]
+pub struct LatencyChecker {
+    max_latency: Duration,
+    max_clock_skew: Duration,
+    histogram: Arc<LatencyHistogram>,
+}
+
+impl LatencyChecker {
+    /// Creates a [`LatencyChecker`] with an empty [`LatencyHistogram`].
+    pub fn new(max_latency: Duration, max_clock_skew: Duration) -> Self {
+        Self {
+            max_latency,
+            max_clock_skew,
+            histogram: Arc::default(),
+        }
+    }
+
+    /// Returns the [`LatencyHistogram`] shared by `self`, e.g. for
+    /// monitoring.
+    pub fn histogram(&self) -> Arc<LatencyHistogram> {
+        self.histogram.clone()
+    }
+
+    /// Parses `sending_time` and checks it against `now`. Latencies of valid
+    /// timestamps are recorded, even if too high; clocks running ahead count
+    /// as zero latency.
+    pub fn check(
+        &self,
+        sending_time: Option<&[u8]>,
+        now: SystemTime,
+    ) -> Result<Duration, LatencyError> {
+        let sending_time = sending_time
+            .and_then(Timestamp::parse)
+            .and_then(|timestamp| timestamp.to_system_time())
+            .ok_or(LatencyError::InvalidSendingTime)?;
+        match now.duration_since(sending_time) {
+            Ok(latency) => {
+                self.histogram.record(latency);
+                if latency > self.max_latency + self.max_clock_skew {
+                    Err(LatencyError::TooOld { latency })
+                } else {
+                    Ok(latency)
+                }
+            }
+            Err(err) => {
+                self.histogram.record(Duration::ZERO);
+                let ahead = err.duration();
+                if ahead > self.max_clock_skew {
+                    Err(LatencyError::InTheFuture { ahead })
+                } else {
+                    Ok(Duration::ZERO)
+                }
+            }
+        }
+    }
+}
+
+/// A lock-free histogram of inbound message latencies, with fixed buckets from
+/// 100µs to 10s.
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKET_BOUNDS_MICROS.len() + 1],
+    sum_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// Adds one observation of `latency`.
+    pub fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let bucket = BUCKET_BOUNDS_MICROS.partition_point(|bound| *bound < micros);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Returns how many latencies were recorded.
+    pub fn count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Returns the average latency, if any was recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        let count = self.count();
+        (count > 0).then(|| Duration::from_micros(self.sum_micros.load(Ordering::Relaxed) / count))
+    }
+
+    /// Returns the highest latency recorded so far.
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_micros.load(Ordering::Relaxed))
+    }
+
+    /// Returns an upper bound of the `quantile` (between 0 and 1) of recorded
+    /// latencies, i.e. the bound of the bucket it falls in, or
+    /// [`LatencyHistogram::max`] for the last bucket.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((count as f64 * quantile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (upper_bound, bucket_count) in self.buckets() {
+            seen += bucket_count;
+            if seen >= rank {
+                return Some(upper_bound.unwrap_or_else(|| self.max()));
+            }
+        }
+        Some(self.max())
+    }
+
+    /// Returns the upper bound and count of every bucket, in increasing
+    /// order. The last bucket has no upper bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        let bounds = BUCKET_BOUNDS_MICROS
+            .iter()
+            .map(|micros| Some(Duration::from_micros(*micros)))
+            .chain([None]);
+        bounds.zip(
+            self.buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    // 2024-01-02 14:00:00 UTC.
+    const SENT: &[u8] = b"20240102-14:00:00.000";
+
+    fn at(millis_after_sent: i64) -> SystemTime {
+        let sent = UNIX_EPOCH + Duration::from_secs(1_704_204_000);
+        let offset = Duration::from_millis(millis_after_sent.unsigned_abs());
+        if millis_after_sent < 0 {
+            sent - offset
+        } else {
+            sent + offset
+        }
+    }
+
+    #[test]
+    fn accepts_latency_within_limit_and_skew() {
+        let checker = LatencyChecker::new(Duration::from_secs(2), Duration::from_secs(1));
+        assert_eq!(
+            checker.check(Some(SENT), at(500)),
+            Ok(Duration::from_millis(500))
+        );
+        assert!(checker.check(Some(SENT), at(2_900)).is_ok());
+        assert!(matches!(
+            checker.check(Some(SENT), at(3_100)),
+            Err(LatencyError::TooOld { .. })
+        ));
+        assert_eq!(checker.check(Some(SENT), at(-900)), Ok(Duration::ZERO));
+        assert!(matches!(
+            checker.check(Some(SENT), at(-1_100)),
+            Err(LatencyError::InTheFuture { .. })
+        ));
+        assert_eq!(
+            checker.check(Some(b"garbage"), at(0)),
+            Err(LatencyError::InvalidSendingTime)
+        );
+        assert_eq!(
+            checker.check(None, at(0)),
+            Err(LatencyError::InvalidSendingTime)
+        );
+        assert_eq!(checker.histogram().count(), 5);
+    }
+
+    #[test]
+    fn histogram_quantiles() {
+        let histogram = LatencyHistogram::default();
+        assert_eq!(histogram.quantile(0.5), None);
+        for _ in 0..9 {
+            histogram.record(Duration::from_micros(80));
+        }
+        histogram.record(Duration::from_secs(20));
+        assert_eq!(histogram.count(), 10);
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_micros(100)));
+        assert_eq!(histogram.quantile(0.9), Some(Duration::from_micros(100)));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_secs(20)));
+        assert_eq!(histogram.max(), Duration::from_secs(20));
+        assert_eq!(histogram.buckets().last(), Some((None, 1)));
+    }
+}
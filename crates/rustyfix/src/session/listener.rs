@@ -0,0 +1,160 @@
+use super::{SeqNumbers, SessionState};
+use std::fmt;
+use std::time::Duration;
+
+/// Lifecycle notifications of a [`FixConnection`](super::FixConnection).
+///
+/// Install it with
+/// [`FixConnection::set_listener`](super::FixConnection::set_listener). Every
+/// method does nothing by default, so implementors only override what they
+/// care about. Listeners are called synchronously while the connection
+/// processes a message, so they should hand anything slow over to another
+/// task.
+pub trait SessionListener: fmt::Debug + Send + Sync {
+    /// Called on every [`SessionState`] transition.
+    fn on_state_change(&mut self, transition: &StateTransition) {
+        let _ = transition;
+    }
+
+    /// Called once the `Logon <A>` handshake has completed.
+    fn on_logon(&mut self, logon: &LogonEvent) {
+        let _ = logon;
+    }
+
+    /// Called when either side starts to log out, i.e. when we send a
+    /// `Logout <5>` or receive one we didn't ask for.
+    fn on_logout(&mut self, logout: &LogoutEvent) {
+        let _ = logout;
+    }
+
+    /// Called when the session moves to [`SessionState::Disconnected`] from
+    /// any other state.
+    fn on_disconnect(&mut self, disconnect: &DisconnectEvent) {
+        let _ = disconnect;
+    }
+
+    /// Called for every `Reject <3>` we send.
+    fn on_reject_sent(&mut self, reject: &RejectEvent) {
+        let _ = reject;
+    }
+
+    /// Called for every `Reject <3>` we receive.
+    fn on_reject_received(&mut self, reject: &RejectEvent) {
+        let _ = reject;
+    }
+
+    /// Called when an inbound sequence gap is detected and a
+    /// `ResendRequest <2>` is sent for it.
+    fn on_sequence_gap(&mut self, gap: &SequenceGapEvent) {
+        let _ = gap;
+    }
+
+    /// Called when the sequence gap being recovered has been filled.
+    fn on_resend_complete(&mut self, resend: &ResendCompleteEvent) {
+        let _ = resend;
+    }
+
+    /// Called when the counterparty stopped responding, even to our
+    /// `TestRequest <1>`.
+    fn on_heartbeat_timeout(&mut self, timeout: &HeartbeatTimeoutEvent) {
+        let _ = timeout;
+    }
+
+    /// Called for every application message dropped by a
+    /// [`Throttle`](super::Throttle) with
+    /// [`ThrottleAction::Reject`](super::ThrottleAction::Reject).
+    fn on_throttle_reject(&mut self, rejected: &ThrottleRejectEvent) {
+        let _ = rejected;
+    }
+}
+
+/// A change of [`SessionState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateTransition {
+    /// The state before the transition.
+    pub from: SessionState,
+    /// The state after the transition.
+    pub to: SessionState,
+}
+
+/// A completed `Logon <A>` handshake.
+#[derive(Debug, Clone, Copy)]
+pub struct LogonEvent {
+    /// The heartbeat interval in use from now on.
+    pub heartbeat: Duration,
+    /// Whether the counterparty's `Logon <A>` had `ResetSeqNumFlag <141>` = Y.
+    pub reset_seq_num_flag: bool,
+    /// The next expected sequence numbers, after the Logon.
+    pub seq_numbers: SeqNumbers,
+}
+
+/// The start of a logout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogoutEvent {
+    /// `true` if the counterparty sent the first `Logout <5>`, `false` if we
+    /// did.
+    pub by_counterparty: bool,
+    /// The `Text <58>` of the `Logout <5>`, if any.
+    pub text: Option<String>,
+}
+
+/// The end of a session.
+#[derive(Debug, Clone, Copy)]
+pub struct DisconnectEvent {
+    /// The state the session was in before.
+    pub previous_state: SessionState,
+    /// The next expected sequence numbers, at the time of the disconnection.
+    pub seq_numbers: SeqNumbers,
+}
+
+/// A session level `Reject <3>`, sent or received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectEvent {
+    /// The `RefSeqNum <45>` of the rejected message.
+    pub ref_seq_num: u64,
+    /// The `RefTagID <371>` of the field at fault, if any.
+    pub ref_tag_id: Option<u32>,
+    /// The `RefMsgType <372>` of the rejected message, if any.
+    pub ref_msg_type: Option<String>,
+    /// The `SessionRejectReason <373>`, if any.
+    pub reason: Option<u32>,
+    /// The `Text <58>`, if any.
+    pub text: Option<String>,
+}
+
+/// An inbound sequence gap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceGapEvent {
+    /// The `MsgSeqNum <34>` we expected.
+    pub expected: u64,
+    /// The `MsgSeqNum <34>` we received instead.
+    pub received: u64,
+}
+
+/// A filled sequence gap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResendCompleteEvent {
+    /// The highest `MsgSeqNum <34>` that had to be recovered.
+    pub last_seq_num: u64,
+}
+
+/// A counterparty that stopped responding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatTimeoutEvent {
+    /// How long ago the last message was received, if ever.
+    pub since_last_received: Option<Duration>,
+    /// The `TestReqID <112>` of our unanswered `TestRequest <1>`, if any.
+    pub test_req_id: Option<String>,
+}
+
+/// An application message dropped because it exceeded a
+/// [`RateLimit`](super::RateLimit).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThrottleRejectEvent {
+    /// The `MsgType <35>` of the message.
+    pub msg_type: Vec<u8>,
+    /// The body of the message, without standard header and trailer.
+    pub body: Vec<u8>,
+    /// How long the message would have had to wait.
+    pub wait: Duration,
+}
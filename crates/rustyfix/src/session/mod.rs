@@ -4,26 +4,96 @@
 //!
 //! The above is a conceptual view of the FIX Session layer, complete with its
 //! state machine and transitions between initiator and acceptor.
-
+//!
+//! # Migrating from the previous `FixConnection`
+//!
+//! The session layer was rewritten around [`FixConnection`] and the
+//! [`Session`] driver. The previous draft of [`FixConnection`] maps to the
+//! current API as follows:
+//!
+//! - `RecoveryAction`: gaps are recovered with `ResendRequest <2>` by
+//!   [`FixConnection::on_inbound_message`] itself. Observe them with
+//!   [`SessionListener::on_sequence_gap`].
+//! - `TestRequestAction`: [`FixConnection::on_test_request_is_due`] sends the
+//!   `TestRequest <1>` and [`FixConnection::on_heartbeat_timeout`] the
+//!   `Logout <5>`; the [`Session`] driver calls both.
+//! - `store_outbound_message`: every outbound message goes to
+//!   [`Backend::on_outbound_message`].
+//! - `get_messages_for_resend`: [`Backend::on_resend_request`] returns the
+//!   stored messages to resend.
+//! - `handle_session_timeout`: use [`FixConnection::initiate_logout`].
+//! - `Response::Session`, `Response::Outbound` and `Response::Resend`: all
+//!   messages to send are returned as [`Response::OutboundBytes`].
+//! - `Response::Inbound`: application messages are returned as
+//!   [`Response::Application`]; session messages are handled internally.
+//! - [`Backend::pending_message`] now removes the message it returns, e.g.
+//!   from the queue of [`MemoryBackend`](backends::MemoryBackend), instead
+//!   of returning the same one until it's removed otherwise.
+
+mod acceptor;
+mod authentication;
 /// Backend implementations for FIX session management.
 pub mod backends;
+mod business_reject;
+mod clock;
 mod config;
+mod connection;
+mod control;
+mod driver;
 mod environment;
 mod errs;
 mod event_loop;
+mod file_log;
 mod heartbeat_rule;
+mod initiator;
+/// Crash-safe message journal used by [`backends::FileBackend`].
+pub mod journal;
+mod latency;
+mod listener;
 mod resend_request_range;
+mod seq_num_store;
 mod seq_numbers;
+mod settings;
+mod tap;
+#[cfg(test)]
+mod test_utils;
+mod throttle;
 
 use crate::tagvalue::Message;
 use crate::{FieldType, SetField};
-pub use config::{Config, Configure};
+pub use acceptor::{
+    AcceptedSession, Acceptor, AcceptorError, SessionDefinition, SessionId, SessionRegistry,
+};
+pub use authentication::{Authenticate, Credentials};
+pub use business_reject::{BusinessReject, BusinessRejectReason};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use config::{Config, Configure, SessionSchedule, SessionTz, Weekday};
+pub use connection::{
+    FixConnection, FixConnector, MessageBuiderTuple, MessageBuilder, NoOpVerifier, Response,
+    SessionState, Verify, split_body_fields,
+};
+pub use control::{SessionControl, SessionControls, SessionStatus};
+pub use driver::{Session, SessionError, SessionHandle, SessionRole};
 pub use environment::Environment;
 pub use event_loop::*;
+pub use file_log::{FileLog, FileLogReader, FileLogSink, LogEntry};
 pub use heartbeat_rule::HeartbeatRule;
+#[cfg(feature = "utils-tokio")]
+pub use initiator::Initiator;
+pub use initiator::ReconnectPolicy;
+pub use latency::{LatencyChecker, LatencyError, LatencyHistogram};
+pub use listener::{
+    DisconnectEvent, HeartbeatTimeoutEvent, LogonEvent, LogoutEvent, RejectEvent,
+    ResendCompleteEvent, SequenceGapEvent, SessionListener, StateTransition, ThrottleRejectEvent,
+};
 pub use resend_request_range::ResendRequestRange;
+pub use seq_num_store::{FileSeqNumStore, MemorySeqNumStore, SeqNumStore};
 pub use seq_numbers::{SeqNumberError, SeqNumbers};
+pub use settings::{SessionSettings, SettingsError, SettingsFile, SettingsSection};
 use std::ops::Range;
+use std::time::SystemTime;
+pub use tap::{ChannelTapSink, FileTapSink, MessageTap, TapOverflow, TapSink, TappedMessage};
+pub use throttle::{RateLimit, Throttle, ThrottleAction, ThrottleMetrics};
 
 /// The owner of a [`FixConnection`]. It can react to events, store incoming
 /// messages, send messages, etc..
@@ -77,17 +147,61 @@ pub trait Backend: Clone {
         }
     }
 
-    /// Callback for processing `ResendRequest` messages.
-    fn on_resend_request(&mut self, range: Range<u64>) -> Result<(), Self::Error>;
+    /// Callback for processing `ResendRequest` messages: returns the stored
+    /// outbound messages within `range`, ordered by `MsgSeqNum <34>`.
+    /// [`FixConnection`] gap fills every message missing from them.
+    fn on_resend_request(&mut self, range: Range<u64>) -> Result<Vec<Vec<u8>>, Self::Error>;
 
     /// Callback for additional logic to execute after a valid [`FixConnection`]
     /// is established with the counterparty.
     fn on_successful_handshake(&mut self) -> Result<(), Self::Error>;
 
+    /// Returns the persisted next expected sequence numbers, if this backend
+    /// keeps track of them. A [`Session`] resumes from these values every time
+    /// it (re)connects. [`None`] by default.
+    fn stored_seq_numbers(&self) -> Option<SeqNumbers> {
+        None
+    }
+
+    /// Called by [`FixConnection`] every time the next expected sequence
+    /// numbers change, including through `SequenceReset <4>` and
+    /// [`SessionControl`] overrides, so that backends persisting them stay
+    /// in sync. Does nothing by default.
+    fn on_seq_numbers_changed(&mut self, seq_numbers: SeqNumbers) -> Result<(), Self::Error> {
+        let _ = seq_numbers;
+        Ok(())
+    }
+
+    /// Returns when the message store was created or last reset, if this
+    /// backend persists it. A [`Session`] resets sequence numbers stored
+    /// before the start of the current [`SessionSchedule`] window, e.g. after
+    /// a restart over the weekend. [`None`] by default.
+    fn creation_time(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Called when both sequence numbers start over from 1, e.g. at the end of
+    /// a [`SessionSchedule`] window. Backends should discard their stored
+    /// messages. Does nothing by default.
+    fn on_sequence_reset(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for every stored application message about to be resent in
+    /// reply to a `ResendRequest <2>`. Returning `false` replaces it with a
+    /// `SequenceReset-GapFill <4>`, e.g. to keep stale orders from reaching
+    /// the market again. `true` by default.
+    fn should_resend(&mut self, message: &[u8]) -> bool {
+        let _ = message;
+        true
+    }
+
     /// Fetches queued messages that need to be sent.
     fn fetch_messages(&mut self) -> Result<&[&[u8]], Self::Error>;
 
-    /// Returns the next pending message to be processed, if any.
+    /// Removes the next pending message, e.g. one queued with
+    /// [`MemoryBackend::queue_message`](backends::MemoryBackend::queue_message),
+    /// and returns it. [`None`] once the queue is empty.
     fn pending_message(&mut self) -> Option<&[u8]>;
 }
 
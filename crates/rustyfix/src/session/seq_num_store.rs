@@ -0,0 +1,135 @@
+use super::SeqNumbers;
+use super::backends::BackendError;
+use super::journal::SeqNumFile;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Persistent storage for the next expected sequence numbers of a
+/// [`FixConnection`](super::FixConnection), independent of any message store.
+///
+/// The connection calls [`SeqNumStore::store`] every time either sequence
+/// number changes, so a restarted process can pick up where the previous one
+/// left off even when its [`Backend`](super::Backend) doesn't keep track of
+/// them.
+pub trait SeqNumStore: fmt::Debug + Send + Sync {
+    /// Returns the stored sequence numbers, or [`None`] if nothing was stored
+    /// yet.
+    fn load(&mut self) -> Result<Option<SeqNumbers>, BackendError>;
+
+    /// Replaces the stored sequence numbers with `seq_numbers`. Either the old
+    /// or the new values must survive a crash, never a mix of both.
+    fn store(&mut self, seq_numbers: SeqNumbers) -> Result<(), BackendError>;
+}
+
+/// A [`SeqNumStore`] that keeps sequence numbers in memory.
+///
+/// Clones share the same values, so a copy kept by the application survives
+/// the [`FixConnection`](super::FixConnection) it was installed on, e.g. to
+/// hand it over to the next one.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySeqNumStore {
+    seq_numbers: Arc<Mutex<Option<SeqNumbers>>>,
+}
+
+impl MemorySeqNumStore {
+    /// Creates an empty [`MemorySeqNumStore`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the stored sequence numbers, if any.
+    pub fn get(&self) -> Option<SeqNumbers> {
+        *self.seq_numbers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SeqNumStore for MemorySeqNumStore {
+    fn load(&mut self) -> Result<Option<SeqNumbers>, BackendError> {
+        Ok(self.get())
+    }
+
+    fn store(&mut self, seq_numbers: SeqNumbers) -> Result<(), BackendError> {
+        *self.seq_numbers.lock().unwrap_or_else(|e| e.into_inner()) = Some(seq_numbers);
+        Ok(())
+    }
+}
+
+/// A [`SeqNumStore`] backed by a QuickFIX-compatible `.seqnums` file, see
+/// [`SeqNumFile`]. Every update atomically replaces the file.
+#[derive(Debug, Clone)]
+pub struct FileSeqNumStore {
+    file: SeqNumFile,
+    fsync: bool,
+}
+
+impl FileSeqNumStore {
+    /// Creates a [`FileSeqNumStore`] at `path`. Updates are flushed to stable
+    /// storage before they replace the previous values.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            file: SeqNumFile::new(path),
+            fsync: true,
+        }
+    }
+
+    /// Skips flushing updates to stable storage. Faster, but the latest values
+    /// may be lost if the machine (not just the process) crashes.
+    pub fn without_fsync(mut self) -> Self {
+        self.fsync = false;
+        self
+    }
+
+    /// Returns the underlying [`SeqNumFile`].
+    pub fn file(&self) -> &SeqNumFile {
+        &self.file
+    }
+}
+
+impl SeqNumStore for FileSeqNumStore {
+    fn load(&mut self) -> Result<Option<SeqNumbers>, BackendError> {
+        self.file.load()
+    }
+
+    fn store(&mut self, seq_numbers: SeqNumbers) -> Result<(), BackendError> {
+        self.file.store(seq_numbers, self.fsync)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_store_is_shared_between_clones() {
+        let store = MemorySeqNumStore::new();
+        let mut copy = store.clone();
+        assert!(copy.load().unwrap().is_none());
+        copy.store(SeqNumbers {
+            next_inbound: 3,
+            next_outbound: 7,
+        })
+        .unwrap();
+        let stored = store.get().unwrap();
+        assert_eq!(stored.next_inbound(), 3);
+        assert_eq!(stored.next_outbound(), 7);
+    }
+
+    #[test]
+    fn file_store_survives_reopening() {
+        let path = std::env::temp_dir().join("test_seq_num_store.seqnums");
+        let _ = std::fs::remove_file(&path);
+        let mut store = FileSeqNumStore::new(&path);
+        assert!(store.load().unwrap().is_none());
+        store
+            .store(SeqNumbers {
+                next_inbound: 12,
+                next_outbound: 5,
+            })
+            .unwrap();
+
+        let loaded = FileSeqNumStore::new(&path).load().unwrap().unwrap();
+        assert_eq!(loaded.next_inbound(), 12);
+        assert_eq!(loaded.next_outbound(), 5);
+    }
+}
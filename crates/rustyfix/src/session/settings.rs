@@ -0,0 +1,898 @@
+use super::{Config, ReconnectPolicy, SessionId, SessionRole, SessionSchedule, SessionTz, Weekday};
+use crate::FieldType;
+use crate::field_types::Time;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Every key understood by [`SettingsFile::sessions`], besides
+/// `AppDataDictionary.<suffix>`, `SocketConnectHost<n>` and
+/// `SocketConnectPort<n>` variants.
+const KNOWN_KEYS: &[&str] = &[
+    "ConnectionType",
+    "BeginString",
+    "SenderCompID",
+    "SenderSubID",
+    "TargetCompID",
+    "TargetSubID",
+    "DefaultApplVerID",
+    "HeartBtInt",
+    "SocketConnectHost",
+    "SocketConnectPort",
+    "SocketAcceptPort",
+    "LogonTimeout",
+    "LogoutTimeout",
+    "PersistMessages",
+    "ReconnectInterval",
+    "FileStorePath",
+    "FileLogPath",
+    "StartTime",
+    "EndTime",
+    "StartDay",
+    "EndDay",
+    "TimeZone",
+    "NonStopSession",
+    "UseDataDictionary",
+    "DataDictionary",
+    "TransportDataDictionary",
+    "AppDataDictionary",
+    "ValidateFieldsOutOfOrder",
+    "ValidateFieldsHaveValues",
+    "ValidateUserDefinedFields",
+    "ValidateLengthAndChecksum",
+    "AllowUnknownMsgFields",
+    "CheckCompID",
+    "CheckLatency",
+    "MaxLatency",
+    "ResendRequestChunkSize",
+    "ResetOnLogon",
+    "ResetOnLogout",
+    "ResetOnDisconnect",
+];
+
+/// Errors found while loading a [`SettingsFile`].
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    /// The settings file couldn't be read.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A line is neither a section header, a `Key=Value` pair, a comment nor
+    /// blank.
+    #[error("line {line}: expected `[SECTION]` or `Key=Value`, found `{text}`")]
+    Syntax {
+        /// The 1-based line number.
+        line: usize,
+        /// The offending line.
+        text: String,
+    },
+    /// A section other than `[DEFAULT]` or `[SESSION]`.
+    #[error("line {line}: unknown section `[{name}]`")]
+    UnknownSection {
+        /// The 1-based line number.
+        line: usize,
+        /// The name of the section.
+        name: String,
+    },
+    /// A value that doesn't fit its key.
+    #[error("invalid value `{value}` for `{key}` in {section}")]
+    InvalidValue {
+        /// Where the key was found.
+        section: SettingsSection,
+        /// The key.
+        key: String,
+        /// The value.
+        value: String,
+    },
+    /// A key that isn't a QuickFIX setting understood by [`SettingsFile`],
+    /// e.g. a typo. See [`SettingsFile::sessions_lenient`] to ignore them.
+    #[error("unknown key `{key}` in {section}")]
+    UnknownKey {
+        /// Where the key was found.
+        section: SettingsSection,
+        /// The key.
+        key: String,
+    },
+    /// A session lacks a mandatory key, even after applying `[DEFAULT]`.
+    #[error("missing `{key}` in {section}")]
+    MissingKey {
+        /// The session.
+        section: SettingsSection,
+        /// The missing key.
+        key: &'static str,
+    },
+}
+
+/// A section of a [`SettingsFile`], as reported by [`SettingsError`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SettingsSection {
+    /// The `[DEFAULT]` section.
+    Default,
+    /// The `n`-th `[SESSION]` section, starting from zero.
+    Session(usize),
+}
+
+impl fmt::Display for SettingsSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "[DEFAULT]"),
+            Self::Session(index) => write!(f, "[SESSION] #{}", index + 1),
+        }
+    }
+}
+
+/// The raw contents of a QuickFIX settings file: `[DEFAULT]` settings shared
+/// by all sessions, and the settings of every `[SESSION]`.
+///
+/// Besides [`SettingsFile::parse`]ing the QuickFIX `.cfg` format,
+/// [`SettingsFile`] can be deserialized with any [`serde`] format, e.g. TOML:
+///
+/// ```toml
+/// [DEFAULT]
+/// ConnectionType = "initiator"
+/// HeartBtInt = 30
+/// CheckLatency = false
+///
+/// [[SESSION]]
+/// BeginString = "FIX.4.4"
+/// SenderCompID = "BANZAI"
+/// TargetCompID = "EXEC"
+/// ```
+///
+/// Numbers and booleans are accepted as well as strings.
+///
+/// # Examples
+///
+/// ```
+/// use rustyfix::session::{SessionRole, SettingsFile};
+/// use std::time::Duration;
+///
+/// let settings = SettingsFile::parse(
+///     "[DEFAULT]\n\
+///      ConnectionType=initiator\n\
+///      HeartBtInt=20\n\
+///      \n\
+///      ; The order entry session.\n\
+///      [SESSION]\n\
+///      BeginString=FIX.4.4\n\
+///      SenderCompID=BANZAI\n\
+///      TargetCompID=EXEC\n\
+///      SocketConnectHost=127.0.0.1\n\
+///      SocketConnectPort=5001\n",
+/// )
+/// .unwrap();
+/// let sessions = settings.sessions().unwrap();
+/// assert_eq!(sessions[0].connection_type, Some(SessionRole::Initiator));
+/// assert_eq!(sessions[0].config.heartbeat, Duration::from_secs(20));
+/// assert_eq!(sessions[0].socket_connect_port, Some(5001));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SettingsFile {
+    /// Settings that apply to every session, unless overridden.
+    #[serde(rename = "DEFAULT", default, deserialize_with = "values")]
+    pub default: BTreeMap<String, String>,
+    /// The settings of each session.
+    #[serde(rename = "SESSION", default, deserialize_with = "sections")]
+    pub sessions: Vec<BTreeMap<String, String>>,
+}
+
+/// A setting value as written in formats with types, e.g. `HeartBtInt = 30`
+/// in TOML.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+}
+
+impl Value {
+    fn into_string(self) -> String {
+        match self {
+            Self::Bool(true) => "Y".to_string(),
+            Self::Bool(false) => "N".to_string(),
+            Self::Integer(integer) => format!("{integer}"),
+            Self::String(string) => string,
+        }
+    }
+}
+
+fn values<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .map(|(key, value)| (key, value.into_string()))
+        .collect())
+}
+
+fn sections<'de, D>(deserializer: D) -> Result<Vec<BTreeMap<String, String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let sections = Vec::<BTreeMap<String, Value>>::deserialize(deserializer)?;
+    Ok(sections
+        .into_iter()
+        .map(|values| {
+            values
+                .into_iter()
+                .map(|(key, value)| (key, value.into_string()))
+                .collect()
+        })
+        .collect())
+}
+
+impl SettingsFile {
+    /// Reads and parses the QuickFIX settings file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the QuickFIX settings format: `[DEFAULT]` and `[SESSION]`
+    /// sections of `Key=Value` lines. Lines starting with `#` or `;` are
+    /// comments. Unknown keys are only reported by [`SettingsFile::sessions`].
+    pub fn parse(text: &str) -> Result<Self, SettingsError> {
+        let mut settings = Self::default();
+        let mut section = None;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim();
+                if name.eq_ignore_ascii_case("DEFAULT") {
+                    section = Some(SettingsSection::Default);
+                } else if name.eq_ignore_ascii_case("SESSION") {
+                    section = Some(SettingsSection::Session(settings.sessions.len()));
+                    settings.sessions.push(BTreeMap::new());
+                } else {
+                    return Err(SettingsError::UnknownSection {
+                        line: line_number,
+                        name: name.to_string(),
+                    });
+                }
+                continue;
+            }
+            let syntax_error = || SettingsError::Syntax {
+                line: line_number,
+                text: line.to_string(),
+            };
+            let (key, value) = line
+                .split_once('=')
+                .filter(|(key, _)| !key.trim().is_empty())
+                .ok_or_else(syntax_error)?;
+            let values = match section {
+                Some(SettingsSection::Default) => &mut settings.default,
+                Some(SettingsSection::Session(index)) => &mut settings.sessions[index],
+                None => return Err(syntax_error()),
+            };
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        Ok(settings)
+    }
+
+    /// Returns the [`SessionSettings`] of every `[SESSION]`, in order, with
+    /// `[DEFAULT]` settings applied. Fails with [`SettingsError::UnknownKey`]
+    /// on keys it doesn't understand, so that typos such as
+    /// `HeartBtInterval=30` don't go unnoticed.
+    pub fn sessions(&self) -> Result<Vec<SessionSettings>, SettingsError> {
+        self.sessions_with(|section, key| {
+            Err(SettingsError::UnknownKey {
+                section,
+                key: key.to_string(),
+            })
+        })
+    }
+
+    /// Like [`SettingsFile::sessions`], but logs and ignores unknown keys,
+    /// e.g. for files shared with other QuickFIX implementations.
+    pub fn sessions_lenient(&self) -> Result<Vec<SessionSettings>, SettingsError> {
+        self.sessions_with(|section, key| {
+            log::warn!("Ignoring unknown key `{key}` in {section}");
+            Ok(())
+        })
+    }
+
+    fn sessions_with(
+        &self,
+        mut on_unknown_key: impl FnMut(SettingsSection, &str) -> Result<(), SettingsError>,
+    ) -> Result<Vec<SessionSettings>, SettingsError> {
+        let mut check_keys = |section, values: &BTreeMap<String, String>| {
+            values
+                .keys()
+                .filter(|key| !is_known_key(key))
+                .try_for_each(|key| on_unknown_key(section, key))
+        };
+        check_keys(SettingsSection::Default, &self.default)?;
+        self.sessions
+            .iter()
+            .enumerate()
+            .map(|(index, values)| {
+                let section = SettingsSection::Session(index);
+                check_keys(section, values)?;
+                let mut merged = self.default.clone();
+                merged.extend(values.iter().map(|(k, v)| (k.clone(), v.clone())));
+                SessionSettings::from_values(section, &merged)
+            })
+            .collect()
+    }
+}
+
+fn is_known_key(key: &str) -> bool {
+    KNOWN_KEYS.contains(&key)
+        || key.starts_with("AppDataDictionary.")
+        || failover_index(key, "SocketConnectHost").is_some()
+        || failover_index(key, "SocketConnectPort").is_some()
+}
+
+/// Returns `n` if `key` is `<prefix><n>`, e.g. `SocketConnectHost1`.
+fn failover_index(key: &str, prefix: &str) -> Option<u32> {
+    let index = key.strip_prefix(prefix)?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok()
+}
+
+/// The settings of one session of a [`SettingsFile`].
+///
+/// Only [`SessionSettings::config`] is applied by a [`Session`](super::Session)
+/// on its own; the other settings are for the caller to apply when setting up
+/// transports, backends and decoders, as documented on each field.
+#[derive(Debug, Clone)]
+pub struct SessionSettings {
+    /// Session layer settings: `BeginString`, `SenderCompID`,
+    /// `TargetCompID`, `DefaultApplVerID`, `HeartBtInt`, schedule keys
+    /// (`StartTime`, `EndTime`, `StartDay`, `EndDay`, `TimeZone`,
+    /// `NonStopSession`) and validation switches (`UseDataDictionary`,
+    /// `ValidateFieldsOutOfOrder`, `ValidateFieldsHaveValues`,
+    /// `ValidateUserDefinedFields`, `AllowUnknownMsgFields`, `CheckCompID`,
+    /// `CheckLatency`, `MaxLatency`, `ResendRequestChunkSize`) and reset
+    /// policies (`ResetOnLogon`, `ResetOnLogout`, `ResetOnDisconnect`).
+    pub config: Config,
+    /// `ConnectionType`: `initiator` or `acceptor`.
+    pub connection_type: Option<SessionRole>,
+    /// `SenderSubID`.
+    pub sender_sub_id: Option<String>,
+    /// `TargetSubID`.
+    pub target_sub_id: Option<String>,
+    /// `SocketConnectHost`.
+    pub socket_connect_host: Option<String>,
+    /// `SocketConnectPort`.
+    pub socket_connect_port: Option<u16>,
+    /// `SocketConnectHost<n>` and `SocketConnectPort<n>`, the failover
+    /// addresses of an initiator, in order of `n`.
+    pub socket_connect_failover: Vec<(String, u16)>,
+    /// `SocketAcceptPort`.
+    pub socket_accept_port: Option<u16>,
+    /// `LogonTimeout`, in seconds. Not applied automatically: pass it to
+    /// [`Acceptor::set_logon_timeout`](super::Acceptor::set_logon_timeout).
+    pub logon_timeout: Option<Duration>,
+    /// `LogoutTimeout`, in seconds. Not applied automatically.
+    pub logout_timeout: Option<Duration>,
+    /// `PersistMessages`: whether sent messages are stored for resending.
+    /// `true` by default. Not applied automatically: the caller chooses a
+    /// [`Backend`](super::Backend) accordingly, e.g. a [`MemoryBackend`]
+    /// with a zero [`MemoryRetention::max_messages`] when `false`.
+    ///
+    /// [`MemoryBackend`]: super::backends::MemoryBackend
+    /// [`MemoryRetention::max_messages`]: super::backends::MemoryRetention::max_messages
+    pub persist_messages: bool,
+    /// `ValidateLengthAndChecksum`: whether `BodyLength <9>` and
+    /// `CheckSum <10>` of inbound messages are checked. `true` by default.
+    /// Not applied automatically: copy it to
+    /// [`Config::verify_checksum`](crate::tagvalue::Config::verify_checksum)
+    /// of the decoder.
+    pub validate_length_and_checksum: bool,
+    /// `ReconnectInterval`, in seconds. Applied by
+    /// [`SessionSettings::reconnect_policy`].
+    pub reconnect_interval: Option<Duration>,
+    /// `FileStorePath`.
+    pub file_store_path: Option<PathBuf>,
+    /// `FileLogPath`.
+    pub file_log_path: Option<PathBuf>,
+    /// `DataDictionary`.
+    pub data_dictionary: Option<PathBuf>,
+    /// `TransportDataDictionary`, for FIXT.1.1 sessions.
+    pub transport_data_dictionary: Option<PathBuf>,
+    /// `AppDataDictionary` and every `AppDataDictionary.<suffix>`, for
+    /// FIXT.1.1 sessions.
+    pub app_data_dictionaries: Vec<PathBuf>,
+}
+
+impl SessionSettings {
+    /// Returns the [`SessionId`] of this session.
+    pub fn session_id(&self) -> SessionId {
+        let mut session_id = SessionId::new(
+            self.config.begin_string.as_str(),
+            self.config.sender_comp_id.as_str(),
+            self.config.target_comp_id.as_str(),
+        );
+        if let Some(sender_sub_id) = &self.sender_sub_id {
+            session_id = session_id.with_sender_sub_id(sender_sub_id);
+        }
+        if let Some(target_sub_id) = &self.target_sub_id {
+            session_id = session_id.with_target_sub_id(target_sub_id);
+        }
+        session_id
+    }
+
+    /// Returns the `host:port` addresses an initiator connects to, in order
+    /// of preference: `SocketConnectHost` and `SocketConnectPort` first, then
+    /// the failover addresses.
+    pub fn socket_connect_addresses(&self) -> Vec<String> {
+        let primary = self
+            .socket_connect_host
+            .as_ref()
+            .zip(self.socket_connect_port);
+        primary
+            .into_iter()
+            .map(|(host, port)| (host.as_str(), port))
+            .chain(
+                self.socket_connect_failover
+                    .iter()
+                    .map(|(host, port)| (host.as_str(), *port)),
+            )
+            .map(|(host, port)| format!("{host}:{port}"))
+            .collect()
+    }
+
+    /// Returns the [`ReconnectPolicy`] of an [`Initiator`](super::Initiator):
+    /// a fixed `ReconnectInterval` between attempts if set, or the default
+    /// policy otherwise.
+    pub fn reconnect_policy(&self) -> ReconnectPolicy {
+        match self.reconnect_interval {
+            Some(interval) => ReconnectPolicy {
+                initial_delay: interval,
+                max_delay: interval,
+                multiplier: 1.0,
+                ..ReconnectPolicy::default()
+            },
+            None => ReconnectPolicy::default(),
+        }
+    }
+
+    fn from_values(
+        section: SettingsSection,
+        values: &BTreeMap<String, String>,
+    ) -> Result<Self, SettingsError> {
+        let reader = Reader { section, values };
+        let mut config = Config::default();
+        config.begin_string = reader.required("BeginString")?;
+        config.sender_comp_id = reader.required("SenderCompID")?;
+        config.target_comp_id = reader.required("TargetCompID")?;
+        config.default_appl_ver_id = reader.string("DefaultApplVerID");
+        if let Some(secs) = reader.parse::<u64>("HeartBtInt")? {
+            config.heartbeat = Duration::from_secs(secs);
+        }
+        config.schedule = reader.schedule()?;
+        let switches: [(&str, &mut bool); 10] = [
+            ("UseDataDictionary", &mut config.use_data_dictionary),
+            (
+                "ValidateFieldsOutOfOrder",
+                &mut config.validate_fields_out_of_order,
+            ),
+            (
+                "ValidateFieldsHaveValues",
+                &mut config.validate_fields_have_values,
+            ),
+            (
+                "ValidateUserDefinedFields",
+                &mut config.validate_user_defined_fields,
+            ),
+            (
+                "AllowUnknownMsgFields",
+                &mut config.allow_unknown_msg_fields,
+            ),
+            ("CheckCompID", &mut config.check_comp_id),
+            ("CheckLatency", &mut config.check_latency),
+            ("ResetOnLogon", &mut config.reset_on_logon),
+            ("ResetOnLogout", &mut config.reset_on_logout),
+            ("ResetOnDisconnect", &mut config.reset_on_disconnect),
+        ];
+        for (key, switch) in switches {
+            if let Some(value) = reader.bool(key)? {
+                *switch = value;
+            }
+        }
+        if let Some(secs) = reader.parse::<u64>("MaxLatency")? {
+            config.max_allowed_latency = Duration::from_secs(secs);
+        }
+        if let Some(chunk_size) = reader.parse::<u64>("ResendRequestChunkSize")? {
+            config.max_resend_range = (chunk_size > 0).then_some(chunk_size);
+        }
+
+        let connection_type = match reader.string("ConnectionType") {
+            None => None,
+            Some(value) if value.eq_ignore_ascii_case("initiator") => Some(SessionRole::Initiator),
+            Some(value) if value.eq_ignore_ascii_case("acceptor") => Some(SessionRole::Acceptor),
+            Some(value) => return Err(reader.invalid("ConnectionType", &value)),
+        };
+        let app_data_dictionaries = values
+            .iter()
+            .filter(|(key, _)| *key == "AppDataDictionary" || key.starts_with("AppDataDictionary."))
+            .map(|(_, path)| PathBuf::from(path))
+            .collect();
+        let mut failover_indices: Vec<u32> = values
+            .keys()
+            .filter_map(|key| failover_index(key, "SocketConnectHost"))
+            .collect();
+        failover_indices.sort_unstable();
+        let socket_connect_failover = failover_indices
+            .into_iter()
+            .map(|index| {
+                let host = format!("SocketConnectHost{index}");
+                let port = format!("SocketConnectPort{index}");
+                let host = reader.string(&host).unwrap_or_default();
+                match reader.parse(&port)? {
+                    Some(port) => Ok((host, port)),
+                    None => Err(SettingsError::MissingKey {
+                        section,
+                        key: "SocketConnectPort<n>",
+                    }),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let seconds = |key| {
+            reader
+                .parse::<u64>(key)
+                .map(|secs| secs.map(Duration::from_secs))
+        };
+        Ok(Self {
+            config,
+            connection_type,
+            sender_sub_id: reader.string("SenderSubID"),
+            target_sub_id: reader.string("TargetSubID"),
+            socket_connect_host: reader.string("SocketConnectHost"),
+            socket_connect_port: reader.parse("SocketConnectPort")?,
+            socket_connect_failover,
+            socket_accept_port: reader.parse("SocketAcceptPort")?,
+            logon_timeout: seconds("LogonTimeout")?,
+            logout_timeout: seconds("LogoutTimeout")?,
+            persist_messages: reader.bool("PersistMessages")?.unwrap_or(true),
+            validate_length_and_checksum: reader.bool("ValidateLengthAndChecksum")?.unwrap_or(true),
+            reconnect_interval: seconds("ReconnectInterval")?,
+            file_store_path: reader.string("FileStorePath").map(PathBuf::from),
+            file_log_path: reader.string("FileLogPath").map(PathBuf::from),
+            data_dictionary: reader.string("DataDictionary").map(PathBuf::from),
+            transport_data_dictionary: reader.string("TransportDataDictionary").map(PathBuf::from),
+            app_data_dictionaries,
+        })
+    }
+}
+
+/// Typed access to the merged settings of one session.
+struct Reader<'a> {
+    section: SettingsSection,
+    values: &'a BTreeMap<String, String>,
+}
+
+impl Reader<'_> {
+    fn string(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn required(&self, key: &'static str) -> Result<String, SettingsError> {
+        self.string(key).ok_or(SettingsError::MissingKey {
+            section: self.section,
+            key,
+        })
+    }
+
+    fn parse<T>(&self, key: &str) -> Result<Option<T>, SettingsError>
+    where
+        T: std::str::FromStr,
+    {
+        self.map(key, |value| value.parse().ok())
+    }
+
+    fn bool(&self, key: &str) -> Result<Option<bool>, SettingsError> {
+        self.map(key, |value| match value {
+            "Y" | "y" => Some(true),
+            "N" | "n" => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Converts the value of `key`, if any, with `f`, which returns [`None`]
+    /// for invalid values.
+    fn map<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<Option<T>, SettingsError> {
+        match self.values.get(key) {
+            None => Ok(None),
+            Some(value) => f(value).map(Some).ok_or_else(|| self.invalid(key, value)),
+        }
+    }
+
+    fn invalid(&self, key: &str, value: &str) -> SettingsError {
+        SettingsError::InvalidValue {
+            section: self.section,
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn schedule(&self) -> Result<SessionSchedule, SettingsError> {
+        if self.bool("NonStopSession")? == Some(true) {
+            return Ok(SessionSchedule::NonStop);
+        }
+        let time = |value: &str| Time::deserialize(value.as_bytes()).ok();
+        let start = self.map("StartTime", time)?;
+        let end = self.map("EndTime", time)?;
+        let start_day = self.map("StartDay", parse_weekday)?;
+        let end_day = self.map("EndDay", parse_weekday)?;
+        let tz = self
+            .map("TimeZone", SessionTz::parse)?
+            .unwrap_or(SessionTz::UTC);
+        let missing = |key| SettingsError::MissingKey {
+            section: self.section,
+            key,
+        };
+        match (start, end, start_day, end_day) {
+            (None, None, None, None) => Ok(SessionSchedule::NonStop),
+            (Some(start), Some(end), None, None) => Ok(SessionSchedule::Daily { start, end, tz }),
+            (Some(start), Some(end), Some(start_day), Some(end_day)) => {
+                Ok(SessionSchedule::Weekly {
+                    start_day,
+                    start,
+                    end_day,
+                    end,
+                    tz,
+                })
+            }
+            (None, ..) => Err(missing("StartTime")),
+            (_, None, ..) => Err(missing("EndTime")),
+            (_, _, None, _) => Err(missing("StartDay")),
+            (_, _, _, None) => Err(missing("EndDay")),
+        }
+    }
+}
+
+/// Parses full English day names and their abbreviations of two or more
+/// letters, in any case, like QuickFIX.
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    const DAYS: [(&str, Weekday); 7] = [
+        ("monday", Weekday::Monday),
+        ("tuesday", Weekday::Tuesday),
+        ("wednesday", Weekday::Wednesday),
+        ("thursday", Weekday::Thursday),
+        ("friday", Weekday::Friday),
+        ("saturday", Weekday::Saturday),
+        ("sunday", Weekday::Sunday),
+    ];
+    let value = value.to_ascii_lowercase();
+    if value.len() < 2 {
+        return None;
+    }
+    DAYS.iter()
+        .find(|(name, _)| name.starts_with(&value))
+        .map(|(_, day)| *day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SETTINGS: &str = "\
+# Shared by every session.
+[DEFAULT]
+ConnectionType=initiator
+HeartBtInt=20
+StartTime=08:00:00
+EndTime=17:00:00
+TimeZone=America/New_York
+ResetOnLogon=Y
+ValidateUserDefinedFields=N
+DataDictionary=spec/FIX44.xml
+
+[SESSION]
+BeginString=FIX.4.4
+SenderCompID=BANZAI
+TargetCompID=EXEC
+SocketConnectHost=127.0.0.1
+SocketConnectPort=5001
+
+; FIXT, open all week.
+[session]
+ConnectionType=acceptor
+BeginString=FIXT.1.1
+DefaultApplVerID=9
+SenderCompID=EXEC
+TargetCompID=BANZAI
+SocketAcceptPort=5002
+StartDay=sun
+EndDay=Friday
+TransportDataDictionary=spec/FIXT11.xml
+AppDataDictionary=spec/FIX50SP2.xml
+AppDataDictionary.FIX.4.4=spec/FIX44.xml
+";
+
+    #[test]
+    fn parses_quickfix_settings() {
+        let sessions = SettingsFile::parse(SETTINGS).unwrap().sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+
+        let initiator = &sessions[0];
+        assert_eq!(initiator.connection_type, Some(SessionRole::Initiator));
+        assert_eq!(
+            initiator.session_id(),
+            SessionId::new("FIX.4.4", "BANZAI", "EXEC")
+        );
+        assert_eq!(initiator.config.heartbeat, Duration::from_secs(20));
+        assert!(!initiator.config.validate_user_defined_fields);
+        assert!(initiator.config.validate_fields_out_of_order);
+        assert!(initiator.config.reset_on_logon);
+        assert!(!initiator.config.reset_on_logout);
+        assert_eq!(initiator.socket_connect_host.as_deref(), Some("127.0.0.1"));
+        assert_eq!(initiator.socket_connect_port, Some(5001));
+        assert_eq!(
+            initiator.data_dictionary,
+            Some(PathBuf::from("spec/FIX44.xml"))
+        );
+        assert_eq!(
+            initiator.config.schedule,
+            SessionSchedule::Daily {
+                start: Time::from_hmsm(8, 0, 0, 0).unwrap(),
+                end: Time::from_hmsm(17, 0, 0, 0).unwrap(),
+                tz: SessionTz::Named(chrono_tz::America::New_York),
+            }
+        );
+
+        let acceptor = &sessions[1];
+        assert_eq!(acceptor.connection_type, Some(SessionRole::Acceptor));
+        assert_eq!(acceptor.config.default_appl_ver_id.as_deref(), Some("9"));
+        assert_eq!(acceptor.socket_accept_port, Some(5002));
+        assert!(matches!(
+            acceptor.config.schedule,
+            SessionSchedule::Weekly {
+                start_day: Weekday::Sunday,
+                end_day: Weekday::Friday,
+                ..
+            }
+        ));
+        assert_eq!(acceptor.app_data_dictionaries.len(), 2);
+    }
+
+    #[test]
+    fn reports_invalid_settings() {
+        let error = |text: &str| {
+            SettingsFile::parse(text)
+                .and_then(|settings| settings.sessions())
+                .unwrap_err()
+                .to_string()
+        };
+        let session = "[SESSION]\nBeginString=FIX.4.4\nSenderCompID=A\nTargetCompID=B\n";
+        assert_eq!(
+            error("[DEFAULT]\nHeartBtInt\n"),
+            "line 2: expected `[SECTION]` or `Key=Value`, found `HeartBtInt`"
+        );
+        assert_eq!(
+            error("[ACCEPTOR]\n"),
+            "line 1: unknown section `[ACCEPTOR]`"
+        );
+        assert_eq!(
+            error(&format!("{session}SocketConnectHost1=backup\n")),
+            "missing `SocketConnectPort<n>` in [SESSION] #1"
+        );
+        assert_eq!(
+            error(&format!("[DEFAULT]\nHeartBtInt=soon\n{session}")),
+            "invalid value `soon` for `HeartBtInt` in [SESSION] #1"
+        );
+        assert_eq!(
+            error(&format!("{session}CheckLatency=maybe\n")),
+            "invalid value `maybe` for `CheckLatency` in [SESSION] #1"
+        );
+        assert_eq!(
+            error(&format!("{session}StartTime=08:00:00\n")),
+            "missing `EndTime` in [SESSION] #1"
+        );
+        assert_eq!(
+            error("[SESSION]\nBeginString=FIX.4.4\n"),
+            "missing `SenderCompID` in [SESSION] #1"
+        );
+    }
+
+    #[test]
+    fn accepts_standard_quickfix_keys() {
+        let settings = SettingsFile::parse(
+            "[SESSION]\n\
+             BeginString=FIX.4.2\n\
+             SenderCompID=BANZAI\n\
+             SenderSubID=DESK\n\
+             TargetCompID=EXEC\n\
+             TargetSubID=ROUTER\n\
+             SocketConnectHost=primary\n\
+             SocketConnectPort=5001\n\
+             SocketConnectHost2=dr\n\
+             SocketConnectPort2=5003\n\
+             SocketConnectHost1=backup\n\
+             SocketConnectPort1=5002\n\
+             LogonTimeout=5\n\
+             LogoutTimeout=2\n\
+             PersistMessages=N\n\
+             ValidateLengthAndChecksum=N\n\
+             TimeZone=Europe/London\n\
+             StartTime=07:00:00\n\
+             EndTime=17:00:00\n\
+             ReconnectInterval=30\n",
+        )
+        .unwrap();
+        let sessions = settings.sessions().unwrap();
+        let session = &sessions[0];
+        assert_eq!(
+            session.session_id(),
+            SessionId::new("FIX.4.2", "BANZAI", "EXEC")
+                .with_sender_sub_id("DESK")
+                .with_target_sub_id("ROUTER")
+        );
+        assert_eq!(
+            session.socket_connect_addresses(),
+            ["primary:5001", "backup:5002", "dr:5003"]
+        );
+        assert_eq!(session.logon_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(session.logout_timeout, Some(Duration::from_secs(2)));
+        assert!(!session.persist_messages);
+        assert!(!session.validate_length_and_checksum);
+        let policy = session.reconnect_policy();
+        assert_eq!(policy.base_delay(0), Duration::from_secs(30));
+        assert_eq!(policy.base_delay(5), Duration::from_secs(30));
+        assert!(matches!(
+            session.config.schedule,
+            SessionSchedule::Daily {
+                tz: SessionTz::Named(chrono_tz::Europe::London),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_unless_lenient() {
+        let settings = SettingsFile::parse(
+            "[DEFAULT]\n\
+             HeartBtInterval=30\n\
+             [SESSION]\n\
+             BeginString=FIX.4.4\n\
+             SenderCompID=A\n\
+             TargetCompID=B\n",
+        )
+        .unwrap();
+        let error = settings.sessions().unwrap_err();
+        assert!(matches!(
+            &error,
+            SettingsError::UnknownKey {
+                section: SettingsSection::Default,
+                key,
+            } if key == "HeartBtInterval"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "unknown key `HeartBtInterval` in [DEFAULT]"
+        );
+        let sessions = settings.sessions_lenient().unwrap();
+        assert_eq!(sessions[0].config.heartbeat, Config::default().heartbeat);
+    }
+
+    #[test]
+    fn deserializes_with_serde() {
+        let json = r#"{
+            "DEFAULT": { "HeartBtInt": 10, "CheckLatency": false },
+            "SESSION": [
+                { "BeginString": "FIX.4.4", "SenderCompID": "A", "TargetCompID": "B" }
+            ]
+        }"#;
+        let settings: SettingsFile = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.default["CheckLatency"], "N");
+        let sessions = settings.sessions().unwrap();
+        assert_eq!(sessions[0].config.heartbeat, Duration::from_secs(10));
+        assert!(!sessions[0].config.check_latency);
+    }
+}
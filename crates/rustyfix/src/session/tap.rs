@@ -0,0 +1,292 @@
+use super::SessionId;
+use super::backends::Direction;
+use futures::channel::mpsc;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::time::SystemTime;
+
+/// A copy of one FIX message handed to [`TapSink`]s.
+#[derive(Debug, Clone)]
+pub struct TappedMessage {
+    /// Whether the message was received or sent.
+    pub direction: Direction,
+    /// The session the message belongs to.
+    pub session_id: Arc<SessionId>,
+    /// When the message was received from, or written for, the counterparty.
+    pub timestamp: SystemTime,
+    /// The raw message, exactly as it was on the wire.
+    pub bytes: Vec<u8>,
+}
+
+/// A destination for the messages copied by a [`MessageTap`].
+///
+/// Sinks run on the background thread of their [`MessageTap`], so slow sinks
+/// never hold up sessions. Any `FnMut(&TappedMessage)` closure is a sink.
+pub trait TapSink: Send + 'static {
+    /// Receives a copy of a message.
+    fn on_message(&mut self, message: &TappedMessage);
+
+    /// Called whenever there are no more messages to deliver for now, e.g. to
+    /// flush buffered writes. Does nothing by default.
+    fn flush(&mut self) {}
+}
+
+impl<F> TapSink for F
+where
+    F: FnMut(&TappedMessage) + Send + 'static,
+{
+    fn on_message(&mut self, message: &TappedMessage) {
+        self(message)
+    }
+}
+
+/// Appends every message to a file, one line per message:
+///
+/// ```text
+/// 20240102-14:00:00.123456 inbound FIX.4.4:SENDER->TARGET 8=FIX.4.4|9=...
+/// ```
+///
+/// SOH separators, shown as `|` above, are written as is.
+#[derive(Debug)]
+pub struct FileTapSink {
+    writer: BufWriter<File>,
+}
+
+impl FileTapSink {
+    /// Opens (or creates) the file at `path` for appending.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    fn write(&mut self, message: &TappedMessage) -> io::Result<()> {
+        let timestamp = chrono::DateTime::<chrono::Utc>::from(message.timestamp);
+        write!(
+            self.writer,
+            "{} {} {} ",
+            timestamp.format("%Y%m%d-%H:%M:%S%.6f"),
+            message.direction.as_str(),
+            message.session_id
+        )?;
+        self.writer.write_all(&message.bytes)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+impl TapSink for FileTapSink {
+    fn on_message(&mut self, message: &TappedMessage) {
+        if let Err(err) = self.write(message) {
+            log::error!("Failed to write a tapped message: {err}");
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Err(err) = self.writer.flush() {
+            log::error!("Failed to flush tapped messages: {err}");
+        }
+    }
+}
+
+/// Forwards every message to an async channel.
+#[derive(Debug)]
+pub struct ChannelTapSink {
+    sender: mpsc::UnboundedSender<TappedMessage>,
+}
+
+impl ChannelTapSink {
+    /// Creates a [`ChannelTapSink`] and the receiving end of its channel.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<TappedMessage>) {
+        let (sender, receiver) = mpsc::unbounded();
+        (Self { sender }, receiver)
+    }
+}
+
+impl TapSink for ChannelTapSink {
+    fn on_message(&mut self, message: &TappedMessage) {
+        // Nobody may be listening anymore.
+        self.sender.unbounded_send(message.clone()).ok();
+    }
+}
+
+/// What a [`MessageTap`] does with a message when its queue is full.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TapOverflow {
+    /// The message is dropped, logged and counted in [`MessageTap::dropped`],
+    /// so that the session never slows down.
+    #[default]
+    Drop,
+    /// The session blocks until there's room in the queue, so that no message
+    /// is ever lost. This stalls the thread the session runs on, including
+    /// other tasks of a single-threaded async runtime.
+    Block,
+}
+
+/// Copies inbound and outbound messages of one or more sessions to
+/// [`TapSink`]s, e.g. for drop copies and compliance archives.
+///
+/// Messages are queued, up to a fixed capacity, for a background thread that
+/// fans them out to all sinks. What happens when the queue is full depends on
+/// the [`TapOverflow`] policy. Install it with
+/// [`FixConnection::set_tap`](super::FixConnection::set_tap); clones share the
+/// same queue and sinks. The thread stops once every clone is gone.
+#[derive(Debug, Clone)]
+pub struct MessageTap {
+    sender: std_mpsc::SyncSender<TappedMessage>,
+    overflow: TapOverflow,
+    dropped: Arc<AtomicU64>,
+}
+
+impl MessageTap {
+    /// Starts the background thread delivering to `sinks`, with room for
+    /// `capacity` messages waiting to be delivered.
+    pub fn spawn(sinks: Vec<Box<dyn TapSink>>, capacity: usize) -> io::Result<Self> {
+        let (sender, receiver) = std_mpsc::sync_channel(capacity);
+        std::thread::Builder::new()
+            .name("rustyfix-tap".to_string())
+            .spawn(move || deliver(sinks, receiver))?;
+        Ok(Self {
+            sender,
+            overflow: TapOverflow::default(),
+            dropped: Arc::default(),
+        })
+    }
+
+    /// Sets what happens when the queue is full. [`TapOverflow::Drop`] by
+    /// default.
+    pub fn with_overflow(mut self, overflow: TapOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Returns the [`TapOverflow`] policy of `self`.
+    pub fn overflow(&self) -> TapOverflow {
+        self.overflow
+    }
+
+    /// Returns how many messages were dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn tap(&self, message: TappedMessage) {
+        let result = match self.overflow {
+            TapOverflow::Drop => self.sender.try_send(message),
+            TapOverflow::Block => self
+                .sender
+                .send(message)
+                .map_err(|err| std_mpsc::TrySendError::Disconnected(err.0)),
+        };
+        match result {
+            Ok(()) => {}
+            Err(std_mpsc::TrySendError::Full(message)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                // Keeps a sustained overflow from flooding the log.
+                if dropped == 1 || dropped.is_multiple_of(1000) {
+                    log::warn!(
+                        "Message tap queue is full, dropped a {} message of {} ({dropped} so far)",
+                        message.direction.as_str(),
+                        message.session_id
+                    );
+                }
+            }
+            Err(std_mpsc::TrySendError::Disconnected(_)) => {
+                log::warn!("The message tap thread is gone");
+            }
+        }
+    }
+}
+
+fn deliver(mut sinks: Vec<Box<dyn TapSink>>, receiver: std_mpsc::Receiver<TappedMessage>) {
+    while let Ok(message) = receiver.recv() {
+        let mut next = Some(message);
+        while let Some(message) = next {
+            for sink in &mut sinks {
+                sink.on_message(&message);
+            }
+            next = receiver.try_recv().ok();
+        }
+        for sink in &mut sinks {
+            sink.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::StreamExt;
+
+    fn message(bytes: &[u8]) -> TappedMessage {
+        TappedMessage {
+            direction: Direction::Inbound,
+            session_id: Arc::new(SessionId::new("FIX.4.4", "SENDER", "TARGET")),
+            timestamp: SystemTime::UNIX_EPOCH,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn fans_out_to_every_sink() {
+        let (first, mut first_rx) = ChannelTapSink::new();
+        let (second, mut second_rx) = ChannelTapSink::new();
+        let tap = MessageTap::spawn(vec![Box::new(first), Box::new(second)], 16).unwrap();
+        tap.tap(message(b"8=FIX.4.4\x01"));
+        assert_eq!(first_rx.next().await.unwrap().bytes, b"8=FIX.4.4\x01");
+        assert_eq!(second_rx.next().await.unwrap().bytes, b"8=FIX.4.4\x01");
+        assert_eq!(tap.dropped(), 0);
+    }
+
+    #[test]
+    fn overflow_policy() {
+        let (started, started_rx) = std_mpsc::channel();
+        let (unblock, blocked) = std_mpsc::channel::<()>();
+        let (delivered, delivered_rx) = std_mpsc::channel();
+        let sink = move |message: &TappedMessage| {
+            started.send(()).unwrap();
+            blocked.recv().ok();
+            delivered.send(message.bytes.clone()).unwrap();
+        };
+        let tap = MessageTap::spawn(vec![Box::new(sink)], 1).unwrap();
+        assert_eq!(tap.overflow(), TapOverflow::Drop);
+        // The first message is held by the sink, the second one waits in the
+        // queue and the third one doesn't fit.
+        tap.tap(message(b"1"));
+        started_rx.recv().unwrap();
+        tap.tap(message(b"2"));
+        tap.tap(message(b"3"));
+        assert_eq!(tap.dropped(), 1);
+
+        let tap = tap.with_overflow(TapOverflow::Block);
+        let blocking = std::thread::spawn(move || {
+            tap.tap(message(b"4"));
+            tap
+        });
+        for _ in 0..3 {
+            unblock.send(()).unwrap();
+        }
+        let tap = blocking.join().unwrap();
+        assert_eq!(tap.dropped(), 1);
+        let delivered: Vec<_> = delivered_rx.iter().take(3).collect();
+        assert_eq!(delivered, [b"1", b"2", b"4"]);
+    }
+
+    #[test]
+    fn file_sink_writes_one_line_per_message() {
+        let path = std::env::temp_dir().join("test_tap.log");
+        let _ = std::fs::remove_file(&path);
+        let mut sink = FileTapSink::open(&path).unwrap();
+        sink.on_message(&message(b"8=FIX.4.4\x0135=0\x01"));
+        sink.flush();
+        let contents = std::fs::read(&path).unwrap();
+        assert_eq!(
+            contents,
+            b"19700101-00:00:00.000000 inbound FIX.4.4:SENDER->TARGET 8=FIX.4.4\x0135=0\x01\n"
+        );
+    }
+}
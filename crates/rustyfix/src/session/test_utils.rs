@@ -0,0 +1,34 @@
+//! Helpers shared by the session tests.
+
+use super::backends::MemoryBackend;
+use super::{
+    Config, FixConnection, NoOpVerifier, Session, SessionHandle, SessionRole, SessionSchedule,
+};
+use crate::Dictionary;
+
+/// A FIX.4.4 [`Session`] between `sender` and `target` without a schedule.
+pub(super) fn session(
+    sender: &str,
+    target: &str,
+    role: SessionRole,
+) -> (Session<MemoryBackend, Config, NoOpVerifier>, SessionHandle) {
+    scheduled_session(sender, target, role, SessionSchedule::NonStop)
+}
+
+/// A FIX.4.4 [`Session`] between `sender` and `target` running on `schedule`.
+pub(super) fn scheduled_session(
+    sender: &str,
+    target: &str,
+    role: SessionRole,
+    schedule: SessionSchedule,
+) -> (Session<MemoryBackend, Config, NoOpVerifier>, SessionHandle) {
+    let mut config = Config::default();
+    config.schedule = schedule;
+    let connection = FixConnection::new(MemoryBackend::new(sender, target), config, NoOpVerifier);
+    Session::new(connection, role, Dictionary::fix44().unwrap())
+}
+
+/// The body of a valid `NewOrderSingle <D>`.
+pub(super) fn order(cl_ord_id: &str) -> Vec<u8> {
+    format!("11={cl_ord_id}\x0154=1\x0160=20240102-14:00:00\x0140=1\x01").into_bytes()
+}
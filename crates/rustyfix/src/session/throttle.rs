@@ -0,0 +1,248 @@
+use quanta::Instant;
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// At most `max_messages` messages in any window of `per`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The maximum number of messages in a window.
+    pub max_messages: usize,
+    /// The length of the window.
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// At most `max_messages` messages per second.
+    pub fn per_second(max_messages: usize) -> Self {
+        Self {
+            max_messages,
+            per: Duration::from_secs(1),
+        }
+    }
+}
+
+/// What happens to an application message that would exceed a [`RateLimit`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ThrottleAction {
+    /// The message waits in the outbound queue while the session keeps
+    /// processing inbound messages and heartbeats.
+    #[default]
+    Queue,
+    /// The session stops taking messages from its handles until the message
+    /// can be sent, but keeps processing inbound messages and heartbeats.
+    Delay,
+    /// The message is dropped and reported to the
+    /// [`SessionListener`](super::SessionListener).
+    Reject,
+}
+
+/// Counters of throttled application messages. They can be read while the
+/// session is running, see [`Throttle::metrics`].
+#[derive(Debug, Default)]
+pub struct ThrottleMetrics {
+    queued: AtomicU64,
+    delayed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl ThrottleMetrics {
+    /// Returns how many times a message was held back in the outbound queue.
+    pub fn queued(&self) -> u64 {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many times the session stopped to wait for a message.
+    pub fn delayed(&self) -> u64 {
+        self.delayed.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many messages were dropped.
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+/// Outbound rate limiting of application messages, globally and by
+/// `MsgType <35>`.
+///
+/// Limits are enforced over sliding windows: a message can only be sent if
+/// fewer than [`RateLimit::max_messages`] messages (of the same type, for
+/// per-type limits) were sent during the last [`RateLimit::per`]. Session-level
+/// messages are never throttled.
+///
+/// # Examples
+///
+/// ```
+/// use rustyfix::session::{RateLimit, Throttle, ThrottleAction};
+///
+/// // At most 50 messages per second, 10 of which can be new orders.
+/// let throttle = Throttle::new(ThrottleAction::Queue)
+///     .with_global_limit(RateLimit::per_second(50))
+///     .with_msg_type_limit(b"D", RateLimit::per_second(10));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    action: ThrottleAction,
+    global: Option<SlidingWindow>,
+    by_msg_type: FxHashMap<Vec<u8>, SlidingWindow>,
+    metrics: Arc<ThrottleMetrics>,
+}
+
+impl Throttle {
+    /// Creates a [`Throttle`] without any limit.
+    pub fn new(action: ThrottleAction) -> Self {
+        Self {
+            action,
+            global: None,
+            by_msg_type: FxHashMap::default(),
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Limits all application messages together.
+    pub fn with_global_limit(mut self, limit: RateLimit) -> Self {
+        self.global = Some(SlidingWindow::new(limit));
+        self
+    }
+
+    /// Limits application messages of type `msg_type`.
+    pub fn with_msg_type_limit(mut self, msg_type: &[u8], limit: RateLimit) -> Self {
+        self.by_msg_type
+            .insert(msg_type.to_vec(), SlidingWindow::new(limit));
+        self
+    }
+
+    /// Returns the [`ThrottleAction`] of `self`.
+    pub fn action(&self) -> ThrottleAction {
+        self.action
+    }
+
+    /// Returns the [`ThrottleMetrics`] of `self`, shared with all of its
+    /// clones.
+    pub fn metrics(&self) -> Arc<ThrottleMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Returns how long a message of type `msg_type` must wait at `now` before
+    /// it can be sent. [`Duration::ZERO`] means right away.
+    pub fn wait_time(&self, msg_type: &[u8], now: Instant) -> Duration {
+        let global = self.global.as_ref().map(|window| window.wait_time(now));
+        let by_msg_type = self
+            .by_msg_type
+            .get(msg_type)
+            .map(|window| window.wait_time(now));
+        global
+            .into_iter()
+            .chain(by_msg_type)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Records that a message of type `msg_type` was sent at `now`.
+    pub fn record(&mut self, msg_type: &[u8], now: Instant) {
+        if let Some(window) = &mut self.global {
+            window.record(now);
+        }
+        if let Some(window) = self.by_msg_type.get_mut(msg_type) {
+            window.record(now);
+        }
+    }
+
+    /// Counts a message that was throttled according to [`Throttle::action`].
+    pub(crate) fn on_throttled(&self) {
+        let counter = match self.action {
+            ThrottleAction::Queue => &self.metrics.queued,
+            ThrottleAction::Delay => &self.metrics.delayed,
+            ThrottleAction::Reject => &self.metrics.rejected,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SlidingWindow {
+    limit: RateLimit,
+    /// Send times within the last window, oldest first.
+    sent: VecDeque<Instant>,
+}
+
+impl SlidingWindow {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            sent: VecDeque::with_capacity(limit.max_messages),
+        }
+    }
+
+    fn wait_time(&self, now: Instant) -> Duration {
+        if self.sent.len() < self.limit.max_messages {
+            return Duration::ZERO;
+        }
+        // The oldest message that must leave the window first.
+        match self.sent.get(self.sent.len() - self.limit.max_messages) {
+            Some(oldest) => (*oldest + self.limit.per).saturating_duration_since(now),
+            // A limit of zero messages.
+            None => self.limit.per,
+        }
+    }
+
+    fn record(&mut self, now: Instant) {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.saturating_duration_since(*sent) >= self.limit.per)
+        {
+            self.sent.pop_front();
+        }
+        self.sent.push_back(now);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sliding_window() {
+        let mut throttle = Throttle::new(ThrottleAction::Queue).with_global_limit(RateLimit {
+            max_messages: 2,
+            per: Duration::from_secs(1),
+        });
+        let start = Instant::now();
+        throttle.record(b"D", start);
+        assert_eq!(throttle.wait_time(b"D", start), Duration::ZERO);
+        throttle.record(b"D", start + Duration::from_millis(400));
+        let now = start + Duration::from_millis(500);
+        assert_eq!(throttle.wait_time(b"D", now), Duration::from_millis(500));
+        assert_eq!(throttle.wait_time(b"F", now), Duration::from_millis(500));
+
+        let now = start + Duration::from_secs(1);
+        assert_eq!(throttle.wait_time(b"D", now), Duration::ZERO);
+        throttle.record(b"D", now);
+        assert_eq!(throttle.wait_time(b"D", now), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn msg_type_limits_only_apply_to_their_type() {
+        let mut throttle = Throttle::new(ThrottleAction::Reject)
+            .with_msg_type_limit(b"D", RateLimit::per_second(1));
+        let now = Instant::now();
+        throttle.record(b"D", now);
+        throttle.record(b"F", now);
+        assert_eq!(throttle.wait_time(b"D", now), Duration::from_secs(1));
+        assert_eq!(throttle.wait_time(b"F", now), Duration::ZERO);
+    }
+
+    #[test]
+    fn metrics_follow_the_action() {
+        let throttle = Throttle::new(ThrottleAction::Reject);
+        let metrics = throttle.metrics();
+        throttle.on_throttled();
+        throttle.clone().on_throttled();
+        assert_eq!(metrics.rejected(), 2);
+        assert_eq!(metrics.queued(), 0);
+    }
+}
@@ -0,0 +1,48 @@
+//! Field tags and `MsgType <35>` values shared by the decoder, validation
+//! and the session layer.
+
+pub(crate) const BEGIN_SEQ_NO: u32 = 7;
+pub(crate) const BEGIN_STRING: u32 = 8;
+pub(crate) const BODY_LENGTH: u32 = 9;
+pub(crate) const CHECK_SUM: u32 = 10;
+pub(crate) const END_SEQ_NO: u32 = 16;
+pub(crate) const MSG_SEQ_NUM: u32 = 34;
+pub(crate) const MSG_TYPE: u32 = 35;
+pub(crate) const NEW_SEQ_NO: u32 = 36;
+pub(crate) const POSS_DUP_FLAG: u32 = 43;
+pub(crate) const REF_SEQ_NUM: u32 = 45;
+pub(crate) const SENDER_COMP_ID: u32 = 49;
+pub(crate) const SENDER_SUB_ID: u32 = 50;
+pub(crate) const SENDING_TIME: u32 = 52;
+pub(crate) const TARGET_COMP_ID: u32 = 56;
+pub(crate) const TARGET_SUB_ID: u32 = 57;
+pub(crate) const TEXT: u32 = 58;
+pub(crate) const ENCRYPT_METHOD: u32 = 98;
+pub(crate) const HEARTBEAT_INT: u32 = 108;
+pub(crate) const TEST_REQ_ID: u32 = 112;
+pub(crate) const ORIG_SENDING_TIME: u32 = 122;
+pub(crate) const GAP_FILL_FLAG: u32 = 123;
+pub(crate) const RESET_SEQ_NUM_FLAG: u32 = 141;
+pub(crate) const REF_TAG_ID: u32 = 371;
+pub(crate) const REF_MSG_TYPE: u32 = 372;
+pub(crate) const SESSION_REJECT_REASON: u32 = 373;
+pub(crate) const BUSINESS_REJECT_REF_ID: u32 = 379;
+pub(crate) const BUSINESS_REJECT_REASON: u32 = 380;
+pub(crate) const TEST_MESSAGE_INDICATOR: u32 = 464;
+pub(crate) const USERNAME: u32 = 553;
+pub(crate) const PASSWORD: u32 = 554;
+pub(crate) const NEW_PASSWORD: u32 = 925;
+pub(crate) const APPL_VER_ID: u32 = 1128;
+pub(crate) const DEFAULT_APPL_VER_ID: u32 = 1137;
+
+/// Returns `true` for the `MsgType <35>` of session-level messages.
+pub(crate) fn is_admin_msg_type(msg_type: &[u8]) -> bool {
+    matches!(msg_type, b"0" | b"1" | b"2" | b"3" | b"4" | b"5" | b"A")
+}
+
+/// Returns the `MsgType <35>` of the raw `message`, without decoding it.
+pub(crate) fn raw_msg_type(message: &[u8]) -> Option<&[u8]> {
+    message
+        .split(|byte| *byte == b'\x01')
+        .find_map(|field| field.strip_prefix(b"35="))
+}
@@ -1,5 +1,6 @@
 use super::{Config, DecodeError, RawDecoder, RawDecoderStreaming, RawFrame};
 use crate::dict::{FixDatatype, IsFieldDefinition};
+use crate::tags::{APPL_VER_ID, MSG_TYPE, is_admin_msg_type};
 use crate::{
     Buffer, Dictionary, FieldMap, FieldType, FieldValueError, GetConfig, RepeatingGroup,
     StreamingDecoder, TagU32,
@@ -9,6 +10,7 @@ use fastrace::prelude::*;
 use nohash_hasher::IntMap;
 use rustc_hash::FxHashMap;
 use smallvec::SmallVec;
+use smartstring::alias::String as SmartString;
 use std::convert::TryInto;
 use std::fmt::Debug;
 use std::iter::FusedIterator;
@@ -54,6 +56,22 @@ pub struct Decoder {
     raw_decoder: RawDecoder,
     tag_lookup: IntMap<u32, FixDatatype>,
     dict: Dictionary,
+    /// Dictionaries of FIXT.1.1 application messages, by `ApplVerID <1128>`.
+    applications: Vec<ApplicationDictionary>,
+    /// Index of the application dictionary for messages without an
+    /// `ApplVerID <1128>`.
+    default_application: Option<usize>,
+    /// Index of the application dictionary of the message being decoded, if
+    /// it's an application message.
+    current_application: Option<usize>,
+    is_application_message: bool,
+}
+
+#[derive(Debug)]
+struct ApplicationDictionary {
+    appl_ver_id: SmartString,
+    dict: Dictionary,
+    tag_lookup: IntMap<u32, FixDatatype>,
 }
 
 impl Decoder {
@@ -63,23 +81,12 @@ impl Decoder {
         Self {
             builder: MessageBuilder::default(),
             raw_decoder: RawDecoder::default(),
-            tag_lookup: dict
-                .fields()
-                .iter()
-                .filter_map(|field| {
-                    let mut fix_type = field.data_type().basetype();
-                    if field.is_num_in_group() {
-                        fix_type = FixDatatype::NumInGroup;
-                    }
-
-                    if fix_type == FixDatatype::Length || fix_type == FixDatatype::NumInGroup {
-                        Some((field.tag().get(), fix_type))
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
+            tag_lookup: tag_lookup(&dict),
             dict,
+            applications: Vec::new(),
+            default_application: None,
+            current_application: None,
+            is_application_message: false,
         }
     }
 
@@ -88,6 +95,77 @@ impl Decoder {
         &self.dict
     }
 
+    /// Decodes application messages with `ApplVerID <1128>` = `appl_ver_id`
+    /// (e.g. `9` for FIX 5.0 SP2) according to `dict`, rather than the
+    /// transport dictionary of `self` (e.g. FIXT 1.1). Session-level messages
+    /// always use the transport dictionary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "fixt11", feature = "fix50sp2"))] {
+    /// use rustyfix::tagvalue::Decoder;
+    /// use rustyfix::Dictionary;
+    ///
+    /// let mut decoder = Decoder::new(Dictionary::fixt11().unwrap());
+    /// decoder.add_application_dictionary("9", Dictionary::fix50sp2().unwrap());
+    /// assert!(decoder.set_default_appl_ver_id(Some("9")));
+    /// # }
+    /// ```
+    pub fn add_application_dictionary(&mut self, appl_ver_id: &str, dict: Dictionary) {
+        let application = ApplicationDictionary {
+            appl_ver_id: appl_ver_id.into(),
+            tag_lookup: tag_lookup(&dict),
+            dict,
+        };
+        match self.application_index(appl_ver_id.as_bytes()) {
+            Some(i) => self.applications[i] = application,
+            None => self.applications.push(application),
+        }
+    }
+
+    /// Returns the application dictionary of `appl_ver_id`, if any.
+    pub fn application_dictionary(&self, appl_ver_id: &str) -> Option<&Dictionary> {
+        self.application_index(appl_ver_id.as_bytes())
+            .map(|i| &self.applications[i].dict)
+    }
+
+    /// Decodes application messages without an `ApplVerID <1128>` according to
+    /// the application dictionary of `appl_ver_id`, i.e. the
+    /// `DefaultApplVerID <1137>` of the counterparty. Returns `false`, leaving
+    /// the default unchanged, if there's no such dictionary. [`None`] goes
+    /// back to the transport dictionary.
+    pub fn set_default_appl_ver_id(&mut self, appl_ver_id: Option<&str>) -> bool {
+        match appl_ver_id {
+            Some(appl_ver_id) => match self.application_index(appl_ver_id.as_bytes()) {
+                Some(i) => {
+                    self.default_application = Some(i);
+                    true
+                }
+                None => false,
+            },
+            None => {
+                self.default_application = None;
+                true
+            }
+        }
+    }
+
+    /// Returns the [`Dictionary`] that the last decoded message was decoded
+    /// with: an application dictionary or the transport dictionary.
+    pub fn message_dictionary(&self) -> &Dictionary {
+        match self.current_application {
+            Some(i) => &self.applications[i].dict,
+            None => &self.dict,
+        }
+    }
+
+    fn application_index(&self, appl_ver_id: &[u8]) -> Option<usize> {
+        self.applications
+            .iter()
+            .position(|application| application.appl_ver_id.as_bytes() == appl_ver_id)
+    }
+
     /// Adds a [`Buffer`] to `self`, turning it into a [`StreamingDecoder`].
     pub fn streaming<B>(self, buffer: B) -> DecoderStreaming<B>
     where
@@ -140,6 +218,8 @@ impl Decoder {
         T: AsRef<[u8]>,
     {
         self.builder.clear();
+        self.current_application = None;
+        self.is_application_message = false;
         self.message_builder_mut().bytes = frame.as_bytes().to_vec(); // Copy instead of reference
         let separator = self.config().separator;
         let payload = frame.payload();
@@ -243,7 +323,25 @@ impl Decoder {
             .map_err(|_| DecodeError::Invalid {
                 reason: format!("Failed to add field {} to message builder", tag.get()),
             })?;
-        let fix_type = self.tag_lookup.get(&tag.get());
+        match tag.get() {
+            MSG_TYPE if !self.applications.is_empty() => {
+                self.is_application_message = !is_admin_msg_type(field_value);
+                if self.is_application_message {
+                    self.current_application = self.default_application;
+                }
+            }
+            APPL_VER_ID if self.is_application_message => {
+                if let Some(i) = self.application_index(field_value) {
+                    self.current_application = Some(i);
+                }
+            }
+            _ => {}
+        }
+        let tag_lookup = match self.current_application {
+            Some(i) => &self.applications[i].tag_lookup,
+            None => &self.tag_lookup,
+        };
+        let fix_type = tag_lookup.get(&tag.get());
         if fix_type == Some(&FixDatatype::NumInGroup) {
             self.builder
                 .state
@@ -280,6 +378,26 @@ impl Decoder {
     }
 }
 
+/// Returns the `Length` and `NumInGroup` fields of `dict`, which drive the
+/// decoding of data fields and repeating groups.
+fn tag_lookup(dict: &Dictionary) -> IntMap<u32, FixDatatype> {
+    dict.fields()
+        .iter()
+        .filter_map(|field| {
+            let mut fix_type = field.data_type().basetype();
+            if field.is_num_in_group() {
+                fix_type = FixDatatype::NumInGroup;
+            }
+
+            if fix_type == FixDatatype::Length || fix_type == FixDatatype::NumInGroup {
+                Some((field.tag().get(), fix_type))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 impl GetConfig for Decoder {
     type Config = Config;
 
@@ -345,6 +463,17 @@ impl<B> DecoderStreaming<B>
 where
     B: Buffer,
 {
+    /// Returns an immutable reference to the underlying [`Decoder`].
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the underlying [`Decoder`], e.g. to
+    /// register application dictionaries.
+    pub fn decoder_mut(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+
     /// Returns an immutable view of the decoded message.
     ///
     /// # Panics
@@ -431,7 +560,7 @@ where
 ///
 /// This type provides safe, read-only access to FIX message data without violating
 /// Rust's aliasing rules. For mutable operations, use [`MessageMut`].
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Message<'a, T> {
     builder: &'a MessageBuilder, // Remove lifetime parameter from MessageBuilder
     phantom: PhantomData<T>,
@@ -934,6 +1063,62 @@ mod test {
         assert_eq!(group.get(0).unwrap().get_raw(278).unwrap(), b"BID" as &[u8]);
     }
 
+    #[cfg(all(feature = "fixt11", feature = "fix50sp2"))]
+    /// Frames a FIXT.1.1 message `body`, without a meaningful checksum.
+    fn fixt_message(body: &str) -> String {
+        format!("8=FIXT.1.1|9={}|{body}10=000|", body.len())
+    }
+
+    #[cfg(all(feature = "fixt11", feature = "fix50sp2"))]
+    fn fixt_decoder() -> Decoder {
+        let mut decoder = Decoder::new(Dictionary::fixt11().unwrap());
+        decoder.config_mut().separator = b'|';
+        decoder.config_mut().verify_checksum = false;
+        decoder.add_application_dictionary("9", Dictionary::fix50sp2().unwrap());
+        decoder
+    }
+
+    #[cfg(all(feature = "fixt11", feature = "fix50sp2"))]
+    #[test]
+    fn application_messages_use_the_dictionary_of_their_appl_ver_id() {
+        let order = fixt_message(
+            "35=D|49=A|56=B|34=2|52=20100304-07:59:30|1128=9|11=X|54=1|453=2|448=P1|447=D|452=1|448=P2|447=D|452=3|",
+        );
+        let mut decoder = fixt_decoder();
+        let message = decoder.decode(order.as_bytes()).unwrap();
+        let parties = message.group(453).unwrap();
+        assert_eq!(parties.len(), 2);
+        assert_eq!(parties.get(0).unwrap().get_raw(448), Some(&b"P1"[..]));
+        // Group fields don't leak to the top level.
+        assert_eq!(message.get_raw(448), None);
+        assert_eq!(message.get_raw(54), Some(&b"1"[..]));
+        assert_eq!(decoder.message_dictionary().version(), "FIX.5.0-SP2");
+
+        // Unknown application versions fall back to the transport dictionary.
+        let unknown_version = order.replace("1128=9", "1128=8");
+        let message = decoder.decode(unknown_version.as_bytes()).unwrap();
+        assert!(message.get_raw(448).is_some());
+        assert_eq!(decoder.message_dictionary().version(), "FIXT.1.1");
+    }
+
+    #[cfg(all(feature = "fixt11", feature = "fix50sp2"))]
+    #[test]
+    fn default_appl_ver_id_applies_to_application_messages_only() {
+        let mut decoder = fixt_decoder();
+        assert!(!decoder.set_default_appl_ver_id(Some("7")));
+        assert!(decoder.set_default_appl_ver_id(Some("9")));
+        let order = fixt_message(
+            "35=D|49=A|56=B|34=2|52=20100304-07:59:30|11=X|54=1|453=1|448=P1|447=D|452=1|",
+        );
+        let message = decoder.decode(order.as_bytes()).unwrap();
+        assert_eq!(message.group(453).unwrap().len(), 1);
+        assert_eq!(decoder.message_dictionary().version(), "FIX.5.0-SP2");
+
+        let heartbeat = fixt_message("35=0|49=A|56=B|34=3|52=20100304-07:59:30|");
+        decoder.decode(heartbeat.as_bytes()).unwrap();
+        assert_eq!(decoder.message_dictionary().version(), "FIXT.1.1");
+    }
+
     #[test]
     fn top_level_tag_after_empty_group() {
         let bytes = b"8=FIX.4.4|9=17|35=X|268=0|346=1|10=171|";
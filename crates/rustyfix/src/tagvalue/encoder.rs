@@ -129,7 +129,7 @@ where
     }
 
     fn write_checksum(&mut self) {
-        let checksum = CheckSum::compute(self.buffer.as_slice());
+        let checksum = CheckSum::compute(&self.buffer.as_slice()[self.initial_buffer_len..]);
         self.set(10, checksum);
     }
 }
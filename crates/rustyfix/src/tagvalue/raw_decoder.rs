@@ -169,6 +169,9 @@ impl<C> GetConfig for RawDecoder<C> {
 #[derive(Debug)]
 enum ParserState {
     Empty,
+    /// The buffer contains the beginning of a header, but not all of it yet.
+    /// The value is the number of bytes to try next.
+    PartialHeader(usize),
     Header(HeaderInfo, usize),
     Failed,
 }
@@ -200,6 +203,7 @@ where
     fn num_bytes_required(&self) -> usize {
         match self.state {
             ParserState::Empty => utils::MIN_FIX_MESSAGE_LEN_IN_BYTES,
+            ParserState::PartialHeader(len) => len,
             ParserState::Header(_, expected_len) => expected_len,
             ParserState::Failed => 0,
         }
@@ -207,9 +211,10 @@ where
 
     fn try_parse(&mut self) -> Result<Option<()>, Self::Error> {
         match self.state {
-            ParserState::Empty => {
-                let header_info =
-                    HeaderInfo::parse(self.buffer.as_slice(), self.config().separator);
+            ParserState::Empty | ParserState::PartialHeader(_) => {
+                let data = self.buffer.as_slice();
+                let separator = self.config().separator;
+                let header_info = HeaderInfo::parse(data, separator);
                 if let Some(header_info) = header_info {
                     let expected_len_of_frame = header_info.field_1.end
                         + 1
@@ -218,6 +223,13 @@ where
 
                     self.state = ParserState::Header(header_info, expected_len_of_frame);
                     Ok(None)
+                } else if HeaderInfo::is_incomplete(data, separator) {
+                    // Headers with zero-padded `BodyLength <9>` values (like
+                    // the ones produced by `Encoder`) can be longer than the
+                    // shortest possible message. Keep reading, one byte at a
+                    // time so that we never read past the header.
+                    self.state = ParserState::PartialHeader(data.len() + 1);
+                    Ok(None)
                 } else {
                     self.state = ParserState::Failed;
                     Err(DecodeError::Invalid {
@@ -282,6 +294,18 @@ struct HeaderInfo {
 }
 
 impl HeaderInfo {
+    /// Upper bound on the length of `8=...|9=...|`. Anything longer is not a
+    /// header we are willing to wait for.
+    const MAX_LEN: usize = 64;
+
+    /// Returns `true` if `data` looks like the beginning of a header which is
+    /// cut short before the end of `BodyLength <9>`.
+    fn is_incomplete(data: &[u8], separator: u8) -> bool {
+        data.len() < Self::MAX_LEN
+            && data.starts_with(b"8=")
+            && data.iter().filter(|byte| **byte == separator).count() < 2
+    }
+
     fn parse(data: &[u8], separator: u8) -> Option<Self> {
         let mut info = Self {
             field_0: 0..1,
@@ -422,4 +446,28 @@ mod test {
             b"35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|"
         );
     }
+
+    #[test]
+    fn streaming_decoder_waits_for_long_headers() {
+        let msg = b"8=FIX.4.4|9=00000005|35=0|10=000|";
+        let mut decoder = new_decoder().streaming(vec![]);
+        let mut i = 0;
+        loop {
+            let buf = decoder.fillable();
+            buf.clone_from_slice(&msg[i..i + buf.len()]);
+            i += buf.len();
+            if decoder.try_parse().unwrap().is_some() {
+                break;
+            }
+        }
+        assert_eq!(i, msg.len());
+        assert_eq!(decoder.raw_frame().payload(), b"35=0|");
+    }
+
+    #[test]
+    fn streaming_decoder_rejects_garbage_header() {
+        let mut decoder = new_decoder().streaming(vec![]);
+        decoder.fillable().clone_from_slice(b"this is not FIX data");
+        assert!(decoder.try_parse().is_err());
+    }
 }
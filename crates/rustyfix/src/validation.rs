@@ -1,8 +1,13 @@
 //! Message validation.
 
-use crate::dict::FixDatatype;
+use crate::dict::{FixDatatype, LayoutItem, LayoutItemKind};
+use crate::tags::APPL_VER_ID;
 use crate::tagvalue::Message;
 use crate::{Dictionary, TagU32};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Tags from this number onwards are reserved for user-defined fields.
+const FIRST_USER_DEFINED_TAG: u32 = 5000;
 
 /// A validator for inbound and outbound FIX messages.
 pub trait Validator {
@@ -61,6 +66,73 @@ pub enum ValidationError {
         /// The reason why the value is out of range
         reason: String,
     },
+    /// The tag isn't defined by the dictionary.
+    #[error("Invalid tag number {tag}")]
+    UnknownTag {
+        /// The unknown tag
+        tag: TagU32,
+    },
+    /// The tag is defined by the dictionary, but not for this message type.
+    #[error("Tag {tag} is not defined for message type '{msg_type}'")]
+    TagNotDefinedForMessageType {
+        /// The unexpected tag
+        tag: TagU32,
+        /// The message type that doesn't define the tag
+        msg_type: String,
+    },
+    /// The field is present but empty.
+    #[error("Tag {tag} specified without a value")]
+    TagWithoutValue {
+        /// The empty field tag
+        tag: TagU32,
+    },
+    /// A header field after the body, or a body field after the trailer.
+    #[error("Tag {tag} specified out of required order")]
+    TagOutOfOrder {
+        /// The misplaced field tag
+        tag: TagU32,
+    },
+    /// No dictionary is available for the `ApplVerID <1128>` of a FIXT.1.1
+    /// application message.
+    #[error("Unsupported ApplVerID '{appl_ver_id}'")]
+    UnsupportedApplVerId {
+        /// The unsupported `ApplVerID <1128>`
+        appl_ver_id: String,
+    },
+}
+
+impl ValidationError {
+    /// Returns the `SessionRejectReason <373>` that corresponds to `self`.
+    pub fn session_reject_reason(&self) -> u32 {
+        match self {
+            Self::UnknownTag { .. } => 0,
+            Self::RequiredFieldMissing { .. } => 1,
+            Self::TagNotDefinedForMessageType { .. } => 2,
+            Self::TagWithoutValue { .. } => 4,
+            Self::InvalidFieldValue { .. } | Self::ValueOutOfRange { .. } => 5,
+            Self::InvalidFieldFormat { .. } => 6,
+            Self::UnknownMessageType { .. } => 11,
+            Self::TagOutOfOrder { .. } => 14,
+            Self::UnsupportedApplVerId { .. } => 18,
+            Self::InvalidMessage { .. } => 99,
+        }
+    }
+
+    /// Returns the tag that `self` is about, i.e. the `RefTagID <371>` of a
+    /// `Reject <3>`, if any.
+    pub fn ref_tag_id(&self) -> Option<u32> {
+        match self {
+            Self::RequiredFieldMissing { tag, .. }
+            | Self::InvalidFieldValue { tag, .. }
+            | Self::UnknownTag { tag }
+            | Self::TagNotDefinedForMessageType { tag, .. }
+            | Self::TagWithoutValue { tag }
+            | Self::TagOutOfOrder { tag } => Some(tag.get()),
+            Self::InvalidFieldFormat { tag, .. } | Self::ValueOutOfRange { tag, .. } => Some(*tag),
+            Self::UnsupportedApplVerId { .. } => Some(APPL_VER_ID),
+            Self::InvalidMessage { .. } | Self::UnknownMessageType { .. } => None,
+        }
+    }
 }
 
 /// A simple [`Validator`] that checks for field presence and correctness.
@@ -96,6 +168,238 @@ impl Validator for SimpleValidator {
     }
 }
 
+/// A [`Validator`] with the message validation switches of QuickFIX, as
+/// used by [`FixConnection`](crate::session::FixConnection) for inbound
+/// messages. Every error maps to a `SessionRejectReason <373>`, see
+/// [`ValidationError::session_reject_reason`].
+///
+/// The message type must be known and all required top-level fields present.
+/// Header fields are the ones of the dictionary's `StandardHeader`, trailer
+/// fields the ones of its `StandardTrailer`.
+#[derive(Debug, Copy, Clone)]
+pub struct SessionValidator {
+    /// Header fields must come first and trailer fields last, i.e.
+    /// `ValidateFieldsOutOfOrder`. `true` by default.
+    pub validate_fields_out_of_order: bool,
+    /// Fields can't be empty, i.e. `ValidateFieldsHaveValues`. `true` by
+    /// default.
+    pub validate_fields_have_values: bool,
+    /// User-defined fields (tags 5000 and above) must be defined for the
+    /// message type, i.e. `ValidateUserDefinedFields`. `true` by default.
+    pub validate_user_defined_fields: bool,
+    /// Other fields may be unknown or not defined for the message type, i.e.
+    /// `AllowUnknownMsgFields`. `false` by default.
+    pub allow_unknown_msg_fields: bool,
+}
+
+impl Default for SessionValidator {
+    fn default() -> Self {
+        Self {
+            validate_fields_out_of_order: true,
+            validate_fields_have_values: true,
+            validate_user_defined_fields: true,
+            allow_unknown_msg_fields: false,
+        }
+    }
+}
+
+impl Validator for SessionValidator {
+    fn validate<T>(&self, msg: &Message<T>, dict: &Dictionary) -> Result<(), ValidationError> {
+        self.validate_fixt(msg, dict, dict)
+    }
+}
+
+impl SessionValidator {
+    /// Validates a FIXT.1.1 `msg` whose header and trailer are defined by
+    /// `transport` (e.g. FIXT 1.1) and whose body is defined by `application`
+    /// (e.g. FIX 5.0 SP2). For other FIX versions both are the same
+    /// dictionary, as in [`Validator::validate`].
+    pub fn validate_fixt<T>(
+        &self,
+        msg: &Message<T>,
+        transport: &Dictionary,
+        application: &Dictionary,
+    ) -> Result<(), ValidationError> {
+        let msg_type = msg
+            .msg_type()
+            .map_err(|_| ValidationError::InvalidMessage {
+                reason: "Unable to extract message type".to_string(),
+            })?;
+        let message_spec = application.message_by_msgtype(msg_type.as_str()).ok_or(
+            ValidationError::UnknownMessageType {
+                msg_type: msg_type.clone(),
+            },
+        )?;
+        let mut body = FxHashSet::default();
+        collect_tags(message_spec.layout(), &mut body);
+        let sections = Sections {
+            header: &component_tags(transport, "StandardHeader"),
+            trailer: &component_tags(transport, "StandardTrailer"),
+            body: &body,
+        };
+        self.validate_sections(msg, &msg_type, application, sections)
+    }
+
+    /// Like [`SessionValidator::validate_fixt`], but with the tags of
+    /// `transport` and `application` collected beforehand. Use this to
+    /// validate many messages against the same dictionaries.
+    pub fn validate_fixt_with_tags<T>(
+        &self,
+        msg: &Message<T>,
+        transport: &MessageTags,
+        application: &Dictionary,
+        application_tags: &MessageTags,
+    ) -> Result<(), ValidationError> {
+        let msg_type = msg
+            .msg_type()
+            .map_err(|_| ValidationError::InvalidMessage {
+                reason: "Unable to extract message type".to_string(),
+            })?;
+        let body = application_tags.bodies.get(msg_type.as_str()).ok_or(
+            ValidationError::UnknownMessageType {
+                msg_type: msg_type.clone(),
+            },
+        )?;
+        let sections = Sections {
+            header: &transport.header,
+            trailer: &transport.trailer,
+            body,
+        };
+        self.validate_sections(msg, &msg_type, application, sections)
+    }
+
+    fn validate_sections<T>(
+        &self,
+        msg: &Message<T>,
+        msg_type: &str,
+        application: &Dictionary,
+        Sections {
+            header,
+            trailer,
+            body,
+        }: Sections,
+    ) -> Result<(), ValidationError> {
+        #[derive(PartialEq, PartialOrd)]
+        enum Section {
+            Header,
+            Body,
+            Trailer,
+        }
+        let mut section = Section::Header;
+        for (tag, value) in msg.fields() {
+            if self.validate_fields_have_values && value.is_empty() {
+                return Err(ValidationError::TagWithoutValue { tag });
+            }
+            let field_section = if header.contains(&tag.get()) {
+                Section::Header
+            } else if trailer.contains(&tag.get()) {
+                Section::Trailer
+            } else {
+                if !body.contains(&tag.get()) {
+                    self.check_field_is_defined(tag, msg_type, application)?;
+                }
+                Section::Body
+            };
+            if field_section < section {
+                if self.validate_fields_out_of_order {
+                    return Err(ValidationError::TagOutOfOrder { tag });
+                }
+            } else {
+                section = field_section;
+            }
+        }
+
+        SimpleValidator::default().validate(msg, application)
+    }
+
+    fn check_field_is_defined(
+        &self,
+        tag: TagU32,
+        msg_type: &str,
+        dict: &Dictionary,
+    ) -> Result<(), ValidationError> {
+        let allowed = if tag.get() >= FIRST_USER_DEFINED_TAG {
+            !self.validate_user_defined_fields
+        } else {
+            self.allow_unknown_msg_fields
+        };
+        if allowed {
+            Ok(())
+        } else if dict.field_by_tag(tag.get()).is_none() {
+            Err(ValidationError::UnknownTag { tag })
+        } else {
+            Err(ValidationError::TagNotDefinedForMessageType {
+                tag,
+                msg_type: msg_type.to_string(),
+            })
+        }
+    }
+}
+
+/// The header, trailer and body tags that [`SessionValidator`] sorts the
+/// fields of a message by.
+#[derive(Debug, Clone, Copy)]
+struct Sections<'a> {
+    header: &'a FxHashSet<u32>,
+    trailer: &'a FxHashSet<u32>,
+    body: &'a FxHashSet<u32>,
+}
+
+/// The tags of the `StandardHeader`, the `StandardTrailer` and the body of
+/// every message type of a [`Dictionary`], for
+/// [`SessionValidator::validate_fixt_with_tags`].
+#[derive(Debug, Clone, Default)]
+pub struct MessageTags {
+    header: FxHashSet<u32>,
+    trailer: FxHashSet<u32>,
+    bodies: FxHashMap<String, FxHashSet<u32>>,
+}
+
+impl MessageTags {
+    /// Collects the tags of `dict`.
+    pub fn new(dict: &Dictionary) -> Self {
+        let bodies = dict
+            .messages()
+            .iter()
+            .map(|message| {
+                let mut body = FxHashSet::default();
+                collect_tags(message.layout(), &mut body);
+                (message.msg_type().to_string(), body)
+            })
+            .collect();
+        Self {
+            header: component_tags(dict, "StandardHeader"),
+            trailer: component_tags(dict, "StandardTrailer"),
+            bodies,
+        }
+    }
+}
+
+fn component_tags(dict: &Dictionary, name: &str) -> FxHashSet<u32> {
+    let mut tags = FxHashSet::default();
+    if let Some(component) = dict.component_by_name(name) {
+        collect_tags(component.items(), &mut tags);
+    }
+    tags
+}
+
+/// Adds the tags of all fields in `items` to `tags`, including those of
+/// components and repeating groups.
+fn collect_tags<'a>(items: impl Iterator<Item = LayoutItem<'a>>, tags: &mut FxHashSet<u32>) {
+    for item in items {
+        match item.kind() {
+            LayoutItemKind::Field(field) => {
+                tags.insert(field.tag().get());
+            }
+            LayoutItemKind::Component(component) => collect_tags(component.items(), tags),
+            LayoutItemKind::Group(len_field, group_items) => {
+                tags.insert(len_field.tag().get());
+                collect_tags(group_items.into_iter(), tags);
+            }
+        }
+    }
+}
+
 /// An advanced [`Validator`] with comprehensive validation capabilities inspired by QuickFIX patterns.
 ///
 /// QuickFIX is a widely-used library for FIX protocol implementations, and its validation patterns
@@ -704,4 +1008,134 @@ mod test {
         let result = validator.validate_field_values(34, b"123", &dict);
         assert!(result.is_ok());
     }
+
+    /// Encodes a `NewOrderSingle <D>` with `fields` after `MsgType <35>`.
+    fn new_order_single(fields: &[(u32, &str)]) -> Vec<u8> {
+        use crate::SetField;
+
+        let mut buffer = Vec::new();
+        let mut encoder = crate::tagvalue::Encoder::new();
+        let mut msg = encoder.start_message(b"FIX.4.4", &mut buffer, b"D");
+        for (tag, value) in fields {
+            msg.set(*tag, *value);
+        }
+        msg.done();
+        buffer
+    }
+
+    fn validate_session(
+        validator: SessionValidator,
+        fields: &[(u32, &str)],
+    ) -> Result<(), ValidationError> {
+        let dict = Dictionary::fix44().unwrap();
+        let mut decoder = Decoder::new(dict.clone());
+        let bytes = new_order_single(fields);
+        let message = decoder.decode(bytes.as_slice()).unwrap();
+        let result = validator.validate(&message, &dict);
+        let tags = MessageTags::new(&dict);
+        assert_eq!(
+            validator.validate_fixt_with_tags(&message, &tags, &dict, &tags),
+            result
+        );
+        result
+    }
+
+    const ORDER: [(u32, &str); 8] = [
+        (49, "CLIENT"),
+        (56, "SERVER"),
+        (34, "2"),
+        (52, "20240102-14:00:00"),
+        (11, "ORDER"),
+        (54, "1"),
+        (60, "20240102-14:00:00"),
+        (40, "1"),
+    ];
+
+    #[test]
+    fn session_validator_accepts_valid_messages() {
+        assert_eq!(
+            validate_session(SessionValidator::default(), &ORDER),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn session_validator_fields_out_of_order() {
+        let mut fields = ORDER.to_vec();
+        // SendingTime <52> after the body.
+        let sending_time = fields.remove(3);
+        fields.push(sending_time);
+        let err = validate_session(SessionValidator::default(), &fields).unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::TagOutOfOrder {
+                tag: TagU32::new(52).unwrap()
+            }
+        );
+        assert_eq!(err.session_reject_reason(), 14);
+        assert_eq!(err.ref_tag_id(), Some(52));
+
+        let lenient = SessionValidator {
+            validate_fields_out_of_order: false,
+            ..SessionValidator::default()
+        };
+        assert_eq!(validate_session(lenient, &fields), Ok(()));
+    }
+
+    #[test]
+    fn session_validator_fields_have_values() {
+        let fields = [ORDER.as_slice(), &[(58, "")]].concat();
+        let err = validate_session(SessionValidator::default(), &fields).unwrap_err();
+        assert_eq!(err.session_reject_reason(), 4);
+        let lenient = SessionValidator {
+            validate_fields_have_values: false,
+            ..SessionValidator::default()
+        };
+        assert_eq!(validate_session(lenient, &fields), Ok(()));
+    }
+
+    #[test]
+    fn session_validator_unknown_fields() {
+        let allow_unknown = SessionValidator {
+            allow_unknown_msg_fields: true,
+            ..SessionValidator::default()
+        };
+
+        // Not defined by the dictionary at all.
+        let unknown = [ORDER.as_slice(), &[(4999, "X")]].concat();
+        let err = validate_session(SessionValidator::default(), &unknown).unwrap_err();
+        assert_eq!(err.session_reject_reason(), 0);
+        assert_eq!(validate_session(allow_unknown, &unknown), Ok(()));
+
+        // `TestReqID <112>` doesn't belong to `NewOrderSingle <D>`.
+        let misplaced = [ORDER.as_slice(), &[(112, "X")]].concat();
+        let err = validate_session(SessionValidator::default(), &misplaced).unwrap_err();
+        assert_eq!(err.session_reject_reason(), 2);
+        assert_eq!(validate_session(allow_unknown, &misplaced), Ok(()));
+    }
+
+    #[test]
+    fn session_validator_user_defined_fields() {
+        let fields = [ORDER.as_slice(), &[(5001, "X")]].concat();
+        let err = validate_session(SessionValidator::default(), &fields).unwrap_err();
+        assert_eq!(err.ref_tag_id(), Some(5001));
+        // `AllowUnknownMsgFields` doesn't cover user-defined fields.
+        let allow_unknown = SessionValidator {
+            allow_unknown_msg_fields: true,
+            ..SessionValidator::default()
+        };
+        assert!(validate_session(allow_unknown, &fields).is_err());
+        let lenient = SessionValidator {
+            validate_user_defined_fields: false,
+            ..SessionValidator::default()
+        };
+        assert_eq!(validate_session(lenient, &fields), Ok(()));
+    }
+
+    #[test]
+    fn session_validator_required_fields() {
+        let err = validate_session(SessionValidator::default(), &ORDER[..7]).unwrap_err();
+        assert_eq!(err.session_reject_reason(), 1);
+        assert_eq!(err.ref_tag_id(), Some(40));
+    }
 }